@@ -0,0 +1,249 @@
+// Diagnostic screens for fatal CPU exceptions and panics.
+//
+// Each report is written twice by the same function: once to the VGA text buffer and once to
+// SERIAL1, so that it survives even if the screen is unreadable. The code that crashed may have
+// been holding the VGA or serial locks, so they are forcibly released first; nothing else will
+// run afterwards anyway.
+
+use core::fmt;
+use core::panic::PanicInfo;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+use x86_64::registers::control::{Cr0, Cr2, Cr3, Cr4};
+use x86_64::structures::idt::{InterruptStackFrame, PageFaultErrorCode};
+use crate::serial::SERIAL1;
+use crate::vga_buffer::{self, BUFFER_HEIGHT, BUFFER_WIDTH, Color, ColorCode, WRITER};
+use crate::{vga_graphics, vga_palette};
+
+/// Appends application details, such as a dump of game state, to the panic report.
+/// It is called once for the screen and once for the serial port.
+pub type PanicHook = fn(&mut dyn fmt::Write) -> fmt::Result;
+
+static PANIC_HOOK: Mutex<Option<PanicHook>> = Mutex::new(None);
+
+pub(crate) fn set_panic_hook(hook: Option<PanicHook>) {
+    *PANIC_HOOK.lock() = hook;
+}
+
+/// Identifies a CPU exception for the crash report.
+pub struct Exception {
+    pub name: &'static str,
+    pub mnemonic: &'static str,
+    pub vector: u8,
+}
+
+/// The error code pushed by an exception, if any, and how to decode it.
+#[derive(Clone, Copy)]
+pub enum ErrorCode {
+    None,
+    Plain(u64),
+    /// A segment selector error code, as pushed by #TS, #NP, #SS and #GP.
+    Selector(u64),
+    /// A page fault error code. The faulting address is read from CR2.
+    PageFault(PageFaultErrorCode),
+}
+
+/// Writes text into a rectangle of the VGA text buffer. A newline returns to the left edge of the
+/// rectangle, long lines wrap, and anything past the bottom is dropped.
+pub struct ScreenWriter {
+    left: usize,
+    right: usize,
+    bottom: usize,
+    col: usize,
+    row: usize,
+    color: ColorCode,
+}
+
+impl ScreenWriter {
+    /// Creates a writer for columns **left** up to (not including) **right** and rows **top** up
+    /// to **bottom**.
+    pub fn new(left: usize, top: usize, right: usize, bottom: usize, color: ColorCode) -> Self {
+        ScreenWriter { left, right, bottom, col: left, row: top, color }
+    }
+
+    fn new_line(&mut self) {
+        self.col = self.left;
+        self.row += 1;
+    }
+}
+
+impl fmt::Write for ScreenWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if c == '\n' {
+                self.new_line();
+                continue;
+            }
+            if self.col >= self.right {
+                self.new_line();
+            }
+            if self.row < self.bottom {
+                let c = if vga_buffer::is_drawable(c) { c } else { 0xfe as char };
+                vga_buffer::plot(c, self.col, self.row, self.color);
+            }
+            self.col += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Prepares the screen for a crash report: releases the VGA lock, returns to text mode with the
+/// classic palette, and fills the screen with **background**.
+///
+/// # Safety
+/// Only call this when nothing else will draw again, since it ignores the VGA lock.
+pub unsafe fn take_over_screen(background: Color) {
+    WRITER.force_unlock();
+    vga_graphics::recover_text_mode();
+    vga_palette::apply_theme(vga_palette::Theme::Classic);
+    for row in 0..BUFFER_HEIGHT {
+        vga_buffer::clear_row(row, background);
+    }
+}
+
+/// Runs **report** against SERIAL1, ignoring the serial lock.
+///
+/// # Safety
+/// Only call this when nothing else will use the serial port again.
+pub unsafe fn mirror_to_serial<F: FnOnce(&mut dyn fmt::Write) -> fmt::Result>(report: F) {
+    SERIAL1.force_unlock();
+    let serial = &mut *SERIAL1.lock();
+    let _ = report(serial);
+}
+
+/// Shows a crash report for **exception** on screen and over serial, then halts for good.
+pub fn exception_screen(exception: &Exception, stack_frame: &InterruptStackFrame, error: ErrorCode) -> ! {
+    interrupts::disable();
+    let color = ColorCode::new(Color::White, Color::Blue);
+    unsafe {
+        take_over_screen(Color::Blue);
+        let mut screen = ScreenWriter::new(2, 1, BUFFER_WIDTH - 2, BUFFER_HEIGHT, color);
+        let _ = write_report(&mut screen, exception, stack_frame, error);
+        mirror_to_serial(|serial| write_report(serial, exception, stack_frame, error));
+    }
+    crate::hlt_loop();
+}
+
+fn write_report(out: &mut dyn fmt::Write, exception: &Exception, stack_frame: &InterruptStackFrame,
+                error: ErrorCode) -> fmt::Result {
+    writeln!(out, "*** EXCEPTION: {} (#{}, vector {}) ***", exception.name, exception.mnemonic, exception.vector)?;
+    writeln!(out)?;
+    match error {
+        ErrorCode::None => {},
+        ErrorCode::Plain(code) => writeln!(out, "Error code: {:#x}", code)?,
+        ErrorCode::Selector(code) => {
+            writeln!(out, "Error code: {:#x}", code)?;
+            write_selector_error(out, code)?;
+        },
+        ErrorCode::PageFault(code) => {
+            writeln!(out, "Error code: {:#x} ({:?})", code.bits(), code)?;
+            let access = if code.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+                "instruction fetch from"
+            } else if code.contains(PageFaultErrorCode::CAUSED_BY_WRITE) {
+                "write to"
+            } else {
+                "read from"
+            };
+            let reason = if code.contains(PageFaultErrorCode::PROTECTION_VIOLATION) {
+                "a protected page"
+            } else {
+                "a non-present page"
+            };
+            let mode = if code.contains(PageFaultErrorCode::USER_MODE) { "user" } else { "kernel" };
+            writeln!(out, "  {} {} in {} mode", access, reason, mode)?;
+            if code.contains(PageFaultErrorCode::MALFORMED_TABLE) {
+                writeln!(out, "  a reserved bit was set in a page table entry")?;
+            }
+            writeln!(out, "Accessed address (CR2): {:#018x}", Cr2::read().as_u64())?;
+        },
+    }
+    writeln!(out)?;
+    writeln!(out, "Stack frame")?;
+    writeln!(out, "  RIP    {:#018x}   CS {:#06x}", stack_frame.instruction_pointer.as_u64(), stack_frame.code_segment)?;
+    writeln!(out, "  RSP    {:#018x}   SS {:#06x}", stack_frame.stack_pointer.as_u64(), stack_frame.stack_segment)?;
+    writeln!(out, "  RFLAGS {:#018x}", stack_frame.cpu_flags)?;
+    writeln!(out)?;
+    writeln!(out, "Control registers")?;
+    writeln!(out, "  CR0 {:#018x}   CR2 {:#018x}", Cr0::read_raw(), Cr2::read().as_u64())?;
+    writeln!(out, "  CR3 {:#018x}   CR4 {:#018x}", Cr3::read().0.start_address().as_u64(), Cr4::read_raw())?;
+    writeln!(out)?;
+    writeln!(out, "The system has been halted.")
+}
+
+fn write_selector_error(out: &mut dyn fmt::Write, code: u64) -> fmt::Result {
+    if code == 0 {
+        return writeln!(out, "  not caused by a segment selector");
+    }
+    let table = match (code >> 1) & 0b11 {
+        0 => "GDT",
+        2 => "LDT",
+        _ => "IDT",
+    };
+    let source = if code & 1 != 0 { ", external event" } else { "" };
+    writeln!(out, "  selector index {} in the {}{}", (code >> 3) & 0x1FFF, table, source)
+}
+
+const BOX_TOP: usize = 1;
+const BOX_BOTTOM: usize = 9;
+const BOX_LEFT: usize = 2;
+const BOX_RIGHT: usize = BUFFER_WIDTH - 3;
+
+/// Shows the panic location and message in a framed box, followed by anything the panic hook
+/// adds, and sends the same report over serial. Then halts for good.
+pub fn panic_screen(info: &PanicInfo) -> ! {
+    interrupts::disable();
+    let text = ColorCode::new(Color::White, Color::Red);
+    let title = ColorCode::new(Color::Yellow, Color::Red);
+    // The hook may have been the thing that panicked; only try it once.
+    let hook = PANIC_HOOK.try_lock().and_then(|mut hook| hook.take());
+    unsafe {
+        take_over_screen(Color::Red);
+        draw_box(BOX_LEFT, BOX_TOP, BOX_RIGHT, BOX_BOTTOM, text);
+        let heading = " KERNEL PANIC ";
+        let mut screen = ScreenWriter::new((BUFFER_WIDTH - heading.len()) / 2, BOX_TOP, BOX_RIGHT, BOX_TOP + 1, title);
+        let _ = fmt::Write::write_str(&mut screen, heading);
+        let mut screen = ScreenWriter::new(BOX_LEFT + 2, BOX_TOP + 2, BOX_RIGHT - 1, BOX_BOTTOM, text);
+        let _ = write_panic(&mut screen, info);
+        if let Some(hook) = hook {
+            let mut screen = ScreenWriter::new(BOX_LEFT, BOX_BOTTOM + 2, BUFFER_WIDTH - 2, BUFFER_HEIGHT, text);
+            let _ = hook(&mut screen);
+        }
+        mirror_to_serial(|serial| {
+            writeln!(serial, "*** KERNEL PANIC ***")?;
+            write_panic(serial, info)?;
+            writeln!(serial)?;
+            match hook {
+                Some(hook) => hook(serial),
+                None => Ok(()),
+            }
+        });
+    }
+    crate::hlt_loop();
+}
+
+fn write_panic(out: &mut dyn fmt::Write, info: &PanicInfo) -> fmt::Result {
+    match info.location() {
+        Some(location) => writeln!(out, "at {}:{}:{}", location.file(), location.line(), location.column())?,
+        None => writeln!(out, "at an unknown location")?,
+    }
+    writeln!(out)?;
+    write!(out, "{}", info.message())
+}
+
+/// Draws a double-line frame using code page 437 box characters.
+fn draw_box(left: usize, top: usize, right: usize, bottom: usize, color: ColorCode) {
+    const HORIZONTAL: char = 0xCD as char;
+    const VERTICAL: char = 0xBA as char;
+    for col in left + 1..right {
+        vga_buffer::plot(HORIZONTAL, col, top, color);
+        vga_buffer::plot(HORIZONTAL, col, bottom, color);
+    }
+    for row in top + 1..bottom {
+        vga_buffer::plot(VERTICAL, left, row, color);
+        vga_buffer::plot(VERTICAL, right, row, color);
+    }
+    vga_buffer::plot(0xC9 as char, left, top, color);
+    vga_buffer::plot(0xBB as char, right, top, color);
+    vga_buffer::plot(0xC8 as char, left, bottom, color);
+    vga_buffer::plot(0xBC as char, right, bottom, color);
+}
@@ -0,0 +1,113 @@
+// Entropy for seeding random number generators.
+//
+// Newer CPUs have hardware random number generators: RDSEED reads the entropy source directly,
+// and RDRAND reads a generator that the CPU reseeds from it. CPUID reports which are present.
+// Without either, the fallback measures timing jitter: how many TSC cycles some PIT reads take,
+// along with the TSC value at each keyboard interrupt, which depends on the player's fingers.
+
+use core::arch::x86_64::{__cpuid, __cpuid_count, _rdrand64_step, _rdseed64_step, _rdtsc};
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use crate::rng::{Rng, SplitMix64};
+
+const RDRAND_BIT: u32 = 1 << 30; // CPUID leaf 1, ECX
+const RDSEED_BIT: u32 = 1 << 18; // CPUID leaf 7, EBX
+/// Intel recommends retrying RDRAND this many times before giving up on it.
+const RETRIES: usize = 10;
+const JITTER_ROUNDS: usize = 64;
+
+const PIT_CHANNEL_0: u16 = 0x40;
+const PIT_COMMAND: u16 = 0x43;
+const PIT_LATCH_CHANNEL_0: u8 = 0x00;
+
+/// Where random_u64() gets its randomness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    RdSeed,
+    RdRand,
+    Jitter,
+}
+
+/// Timing of keyboard interrupts, stirred in by the keyboard interrupt handler.
+static POOL: AtomicU64 = AtomicU64::new(0);
+/// Makes every call to jitter_u64() different, even with no jitter at all.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// True if the CPU supports the RDRAND instruction.
+#[allow(unused_unsafe)] // __cpuid() is only a safe function on newer toolchains
+pub fn has_rdrand() -> bool {
+    unsafe { __cpuid(1).ecx & RDRAND_BIT != 0 }
+}
+
+/// True if the CPU supports the RDSEED instruction.
+#[allow(unused_unsafe)] // __cpuid() is only a safe function on newer toolchains
+pub fn has_rdseed() -> bool {
+    unsafe { __cpuid(0).eax >= 7 && __cpuid_count(7, 0).ebx & RDSEED_BIT != 0 }
+}
+
+/// Returns the best source this CPU offers.
+pub fn source() -> Source {
+    if has_rdseed() {
+        Source::RdSeed
+    } else if has_rdrand() {
+        Source::RdRand
+    } else {
+        Source::Jitter
+    }
+}
+
+/// Returns 64 random bits, from RDSEED or RDRAND when the CPU has them, or from timing jitter
+/// otherwise. Good for seeding games; not meant for cryptography.
+pub fn random_u64() -> u64 {
+    let hardware = match source() {
+        Source::RdSeed => unsafe { rdseed() }.or_else(|| unsafe { rdrand() }),
+        Source::RdRand => unsafe { rdrand() },
+        Source::Jitter => None,
+    };
+    hardware.unwrap_or_else(jitter_u64)
+}
+
+#[target_feature(enable = "rdseed")]
+unsafe fn rdseed() -> Option<u64> {
+    let mut value = 0;
+    // RDSEED fails when the entropy source is drained, so it is worth waiting a moment.
+    (0..RETRIES).any(|_| _rdseed64_step(&mut value) == 1).then_some(value)
+}
+
+#[target_feature(enable = "rdrand")]
+unsafe fn rdrand() -> Option<u64> {
+    let mut value = 0;
+    (0..RETRIES).any(|_| _rdrand64_step(&mut value) == 1).then_some(value)
+}
+
+/// Returns 64 bits gathered from timing jitter and keyboard timing, without using RDRAND or
+/// RDSEED.
+pub fn jitter_u64() -> u64 {
+    let mut pit_counter: Port<u8> = Port::new(PIT_CHANNEL_0);
+    let mut pit_command: Port<u8> = Port::new(PIT_COMMAND);
+    let mut acc = POOL.load(Ordering::Relaxed) ^ COUNTER.fetch_add(1, Ordering::Relaxed);
+    for _ in 0..JITTER_ROUNDS {
+        let start = read_tsc();
+        // Port I/O goes out over the bus, so its duration varies more than plain instructions do.
+        let pit = interrupts::without_interrupts(|| unsafe {
+            pit_command.write(PIT_LATCH_CHANNEL_0);
+            let low = pit_counter.read() as u64;
+            let high = pit_counter.read() as u64;
+            high << 8 | low
+        });
+        let elapsed = read_tsc().wrapping_sub(start);
+        acc = SplitMix64::new(acc ^ elapsed.rotate_left(32) ^ pit).next_u64();
+    }
+    acc
+}
+
+/// Called by the keyboard interrupt handler.
+pub(crate) fn add_interrupt_timing() {
+    let sample = SplitMix64::new(read_tsc()).next_u64();
+    POOL.fetch_xor(sample, Ordering::Relaxed);
+}
+
+fn read_tsc() -> u64 {
+    unsafe { _rdtsc() }
+}
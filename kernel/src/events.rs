@@ -0,0 +1,175 @@
+// Events passed from the interrupt handlers to the main loop.
+//
+// The interrupt handlers are the only producers, and since interrupt handlers do not interrupt
+// each other, they never push at the same time. The main loop (or a single task) is the only
+// consumer. That makes a single-producer, single-consumer ring buffer sufficient, and
+// neither side ever waits for the other.
+
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+use futures_util::task::AtomicWaker;
+use pc_keyboard::DecodedKey;
+use crate::keyboard::RawKeyEvent;
+use x86_64::instructions::interrupts;
+
+/// Something that happened in an interrupt handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The timer fired.
+    Tick,
+    /// A key was pressed.
+    Key(DecodedKey),
+    /// The real-time clock's periodic interrupt fired.
+    Rtc,
+    /// A key went down or came up. This arrives just before the matching **Key** event, if any.
+    RawKey(RawKeyEvent),
+    /// A byte arrived on the first serial port.
+    Serial(u8),
+    /// A byte arrived on the second serial port.
+    Serial2(u8),
+}
+
+/// Number of events that can wait in the queue. It must be a power of two.
+pub const EVENT_QUEUE_SIZE: usize = 64;
+
+/// A bounded lock-free queue for one producer and one consumer. When it is full, new events are
+/// dropped and counted.
+pub struct EventQueue<const N: usize> {
+    slots: [UnsafeCell<Event>; N],
+    /// Total number of events ever popped. Only the consumer writes it.
+    head: AtomicUsize,
+    /// Total number of events ever pushed. Only the producer writes it.
+    tail: AtomicUsize,
+    overflows: AtomicUsize,
+}
+
+// Safety: the producer only writes the slot at tail before publishing it, and the consumer only
+// reads the slot at head after seeing it published, so no slot is accessed from both sides at once.
+unsafe impl<const N: usize> Sync for EventQueue<N> {}
+
+impl<const N: usize> EventQueue<N> {
+    pub const fn new() -> Self {
+        assert!(N.is_power_of_two());
+        EventQueue {
+            slots: [const { UnsafeCell::new(Event::Tick) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds **event** to the back of the queue. Returns false, and counts the overflow, if the
+    /// queue is full.
+    ///
+    /// # Safety
+    /// Only one producer may push at a time.
+    pub unsafe fn push(&self, event: Event) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == N {
+            self.overflows.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        *self.slots[tail % N].get() = event;
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    /// Removes the event at the front of the queue, if any.
+    ///
+    /// # Safety
+    /// Only one consumer may pop at a time.
+    pub unsafe fn pop(&self) -> Option<Event> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let event = *self.slots[head % N].get();
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(event)
+    }
+
+    /// Returns the number of events waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.tail.load(Ordering::Acquire).wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many events have been dropped because the queue was full.
+    pub fn overflow_count(&self) -> usize {
+        self.overflows.load(Ordering::Relaxed)
+    }
+}
+
+impl<const N: usize> Default for EventQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static EVENTS: EventQueue<EVENT_QUEUE_SIZE> = EventQueue::new();
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Called by the interrupt handlers.
+pub(crate) fn post(event: Event) {
+    // Safety: only interrupt handlers push, and they never run at the same time.
+    if unsafe { EVENTS.push(event) } {
+        WAKER.wake();
+    }
+}
+
+/// Returns how many events have been dropped because the event handler fell behind.
+pub fn overflow_count() -> usize {
+    EVENTS.overflow_count()
+}
+
+/// Calls **handler** on each event as it arrives, halting the CPU whenever the queue is empty.
+pub(crate) fn run_event_loop(handler: fn(Event)) -> ! {
+    loop {
+        // Interrupts are disabled while checking, so that an event cannot arrive between
+        // finding the queue empty and halting.
+        interrupts::disable();
+        // Safety: this is the only consumer.
+        match unsafe { EVENTS.pop() } {
+            Some(event) => {
+                interrupts::enable();
+                handler(event);
+            }
+            None => interrupts::enable_and_hlt(),
+        }
+    }
+}
+
+/// The same as [run_event_loop], as a task for the executor.
+pub(crate) async fn dispatch_events(handler: fn(Event)) {
+    loop {
+        let event = NextEvent.await;
+        handler(event);
+    }
+}
+
+struct NextEvent;
+
+impl Future for NextEvent {
+    type Output = Event;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Event> {
+        // Safety: only the dispatch_events task consumes events when the executor runs.
+        if let Some(event) = unsafe { EVENTS.pop() } {
+            return Poll::Ready(event);
+        }
+        WAKER.register(cx.waker());
+        match unsafe { EVENTS.pop() } {
+            Some(event) => {
+                WAKER.take();
+                Poll::Ready(event)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use core::fmt::Write;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+use crate::{println, gdt, task, events, keyboard, rtc, entropy, serial};
+use crate::events::Event;
+use crate::crash::{self, ErrorCode, Exception};
+use lazy_static::lazy_static;
+use pic8259::ChainedPics;
+use spin::Mutex;
+use crate::HandlerTable;
+
+// This code is largely Copyright (c) 2019 Philipp Oppermann.
+// Gabriel Ferrer added:
+// - HANDLERS variable.
+// - Use of HANDLERS in init_idt, timer_interrupt_handler, keyboard_interrupt_handler
+//
+// Added since then:
+// - Handlers for the remaining CPU exceptions, which show the crash screen, or log #DB and NMI
+// - Feeding the timer ticks and scancodes to the async tasks
+// - Posting events for the event loop
+// - Decoding with the selected layout and forwarding raw key events
+// - The real-time clock interrupt
+// - Keyboard timing for the entropy pool
+// - The serial ports' receive interrupts
+
+lazy_static! {
+    static ref HANDLERS: Mutex<Option<HandlerTable>> = Mutex::new(None);
+}
+
+lazy_static! {
+    static ref IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        idt.divide_error.set_handler_fn(divide_error_handler);
+        idt.debug.set_handler_fn(debug_handler);
+        idt.non_maskable_interrupt.set_handler_fn(non_maskable_interrupt_handler);
+        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        idt.overflow.set_handler_fn(overflow_handler);
+        idt.bound_range_exceeded.set_handler_fn(bound_range_exceeded_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
+        idt.device_not_available.set_handler_fn(device_not_available_handler);
+        unsafe {
+            idt.double_fault.set_handler_fn(double_fault_handler)
+                .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt.invalid_tss.set_handler_fn(invalid_tss_handler);
+        idt.segment_not_present.set_handler_fn(segment_not_present_handler);
+        idt.stack_segment_fault.set_handler_fn(stack_segment_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
+        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.x87_floating_point.set_handler_fn(x87_floating_point_handler);
+        idt.alignment_check.set_handler_fn(alignment_check_handler);
+        idt.machine_check.set_handler_fn(machine_check_handler);
+        idt.simd_floating_point.set_handler_fn(simd_floating_point_handler);
+        idt.virtualization.set_handler_fn(virtualization_handler);
+        idt.security_exception.set_handler_fn(security_exception_handler);
+        idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
+        idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
+        idt[InterruptIndex::Serial1.as_usize()].set_handler_fn(serial_interrupt_handler);
+        idt[InterruptIndex::Serial2.as_usize()].set_handler_fn(serial2_interrupt_handler);
+        idt[InterruptIndex::Rtc.as_usize()].set_handler_fn(rtc_interrupt_handler);
+        idt
+    };
+}
+
+/// Initializes the interrupt table with the given interrupt handlers.
+pub fn init_idt(handlers: HandlerTable) {
+    *(HANDLERS.lock()) = Some(handlers);
+    IDT.load();
+}
+
+extern "x86-interrupt" fn breakpoint_handler(
+    stack_frame: InterruptStackFrame)
+{
+    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn double_fault_handler(
+    stack_frame: InterruptStackFrame, error_code: u64) -> !
+{
+    crash::exception_screen(
+        &Exception { name: "DOUBLE FAULT", mnemonic: "DF", vector: 8 },
+        &stack_frame, ErrorCode::Plain(error_code))
+}
+
+extern "x86-interrupt" fn page_fault_handler(
+    stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode)
+{
+    crash::exception_screen(
+        &Exception { name: "PAGE FAULT", mnemonic: "PF", vector: 14 },
+        &stack_frame, ErrorCode::PageFault(error_code))
+}
+
+extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+    crash::exception_screen(
+        &Exception { name: "MACHINE CHECK", mnemonic: "MC", vector: 18 },
+        &stack_frame, ErrorCode::None)
+}
+
+/// Defines a handler for an exception that the kernel can carry on after, like a breakpoint. It is
+/// logged to SERIAL1 and the interrupted code resumes. Either exception can arrive while SERIAL1
+/// is locked, and since they cannot be masked, the log is skipped rather than waiting for a lock
+/// that will never be released.
+macro_rules! logged_handler {
+    ($handler:ident, $name:expr) => {
+        extern "x86-interrupt" fn $handler(stack_frame: InterruptStackFrame) {
+            if let Some(mut port) = serial::SERIAL1.try_lock() {
+                let _ = writeln!(port, "EXCEPTION: {}\n{:#?}", $name, stack_frame);
+            }
+        }
+    };
+}
+
+logged_handler!(debug_handler, "DEBUG");
+logged_handler!(non_maskable_interrupt_handler, "NON-MASKABLE INTERRUPT");
+
+/// Defines a handler that shows the crash screen for an exception.
+/// The error code, if the exception pushes one, is decoded with the given ErrorCode variant.
+macro_rules! fatal_handler {
+    ($handler:ident, $name:expr, $mnemonic:expr, $vector:expr) => {
+        extern "x86-interrupt" fn $handler(stack_frame: InterruptStackFrame) {
+            crash::exception_screen(
+                &Exception { name: $name, mnemonic: $mnemonic, vector: $vector },
+                &stack_frame, ErrorCode::None)
+        }
+    };
+    ($handler:ident, $name:expr, $mnemonic:expr, $vector:expr, $error:path) => {
+        extern "x86-interrupt" fn $handler(stack_frame: InterruptStackFrame, error_code: u64) {
+            crash::exception_screen(
+                &Exception { name: $name, mnemonic: $mnemonic, vector: $vector },
+                &stack_frame, $error(error_code))
+        }
+    };
+}
+
+fatal_handler!(divide_error_handler, "DIVIDE ERROR", "DE", 0);
+fatal_handler!(overflow_handler, "OVERFLOW", "OF", 4);
+fatal_handler!(bound_range_exceeded_handler, "BOUND RANGE EXCEEDED", "BR", 5);
+fatal_handler!(invalid_opcode_handler, "INVALID OPCODE", "UD", 6);
+fatal_handler!(device_not_available_handler, "DEVICE NOT AVAILABLE", "NM", 7);
+fatal_handler!(invalid_tss_handler, "INVALID TSS", "TS", 10, ErrorCode::Selector);
+fatal_handler!(segment_not_present_handler, "SEGMENT NOT PRESENT", "NP", 11, ErrorCode::Selector);
+fatal_handler!(stack_segment_fault_handler, "STACK-SEGMENT FAULT", "SS", 12, ErrorCode::Selector);
+fatal_handler!(general_protection_fault_handler, "GENERAL PROTECTION FAULT", "GP", 13, ErrorCode::Selector);
+fatal_handler!(x87_floating_point_handler, "x87 FLOATING-POINT EXCEPTION", "MF", 16);
+fatal_handler!(alignment_check_handler, "ALIGNMENT CHECK", "AC", 17, ErrorCode::Plain);
+fatal_handler!(simd_floating_point_handler, "SIMD FLOATING-POINT EXCEPTION", "XM", 19);
+fatal_handler!(virtualization_handler, "VIRTUALIZATION EXCEPTION", "VE", 20);
+fatal_handler!(security_exception_handler, "SECURITY EXCEPTION", "SX", 30, ErrorCode::Plain);
+
+const PIC_1_OFFSET: u8 = 32;
+const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+
+pub static PICS: Mutex<ChainedPics> =
+    Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+enum InterruptIndex {
+    Timer = PIC_1_OFFSET,
+    Keyboard,
+    Serial2 = PIC_1_OFFSET + serial::COM2_IRQ,
+    Serial1 = PIC_1_OFFSET + serial::COM1_IRQ,
+    Rtc = PIC_2_OFFSET,
+}
+
+/// The secondary PIC's line into the primary PIC.
+const CASCADE_IRQ: u8 = 2;
+/// The RTC's line on the secondary PIC (IRQ 8).
+const RTC_IRQ: u8 = 0;
+
+/// Unmasks IRQ 8 and turns on the RTC's periodic interrupt at its slowest rate.
+pub(crate) fn enable_rtc_interrupt() {
+    unsafe {
+        let mut pics = PICS.lock();
+        let [primary, secondary] = pics.read_masks();
+        pics.write_masks(primary & !(1 << CASCADE_IRQ), secondary & !(1 << RTC_IRQ));
+    }
+    rtc::enable_periodic_interrupt(rtc::SLOWEST_RATE);
+}
+
+/// Unmasks IRQ 4, on which SERIAL1 interrupts when it receives a byte.
+pub(crate) fn enable_serial_interrupt() {
+    // initializing the port also turns on its receive interrupt
+    lazy_static::initialize(&serial::SERIAL1);
+    unmask_primary(serial::COM1_IRQ);
+}
+
+/// Unmasks IRQ 3, on which SERIAL2 interrupts when it receives a byte.
+pub(crate) fn enable_serial2_interrupt() {
+    lazy_static::initialize(&serial::SERIAL2);
+    unmask_primary(serial::COM2_IRQ);
+}
+
+fn unmask_primary(irq: u8) {
+    unsafe {
+        let mut pics = PICS.lock();
+        let [primary, secondary] = pics.read_masks();
+        pics.write_masks(primary & !(1 << irq), secondary);
+    }
+}
+
+impl InterruptIndex {
+    fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    fn as_usize(self) -> usize {
+        usize::from(self.as_u8())
+    }
+}
+
+extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    task::timer::tick();
+    let h = &*HANDLERS.lock();
+    if let Some(handler) = h {
+        handler.handle_timer();
+        if handler.has_event_loop() {
+            events::post(Event::Tick);
+        }
+    }
+    unsafe {
+        PICS.lock()
+            .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
+    }
+}
+
+extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    use x86_64::instructions::port::Port;
+
+    let mut port = Port::new(0x60);
+
+    let scancode: u8 = unsafe { port.read() };
+    entropy::add_interrupt_timing();
+    task::keyboard::add_scancode(scancode);
+    if let Some((raw, decoded)) = keyboard::decode(scancode) {
+        let h = &*HANDLERS.lock();
+        if let Some(handler) = h {
+            handler.handle_raw_keyboard(raw);
+            if handler.has_event_loop() {
+                events::post(Event::RawKey(raw));
+            }
+            if let Some(key) = decoded {
+                handler.handle_keyboard(key);
+                if handler.has_event_loop() {
+                    events::post(Event::Key(key));
+                }
+            }
+        }
+    }
+
+    unsafe {
+        PICS.lock()
+            .notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
+    }
+}
+
+extern "x86-interrupt" fn serial_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    // the port can hold several bytes before it interrupts
+    while let Some(byte) = serial::receive(serial::COM1) {
+        let h = &*HANDLERS.lock();
+        if let Some(handler) = h {
+            handler.handle_serial(byte);
+            if handler.has_event_loop() {
+                events::post(Event::Serial(byte));
+            }
+        }
+    }
+    unsafe {
+        PICS.lock()
+            .notify_end_of_interrupt(InterruptIndex::Serial1.as_u8());
+    }
+}
+
+extern "x86-interrupt" fn serial2_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    while let Some(byte) = serial::receive(serial::COM2) {
+        let h = &*HANDLERS.lock();
+        if let Some(handler) = h {
+            handler.handle_serial2(byte);
+            if handler.has_event_loop() {
+                events::post(Event::Serial2(byte));
+            }
+        }
+    }
+    unsafe {
+        PICS.lock()
+            .notify_end_of_interrupt(InterruptIndex::Serial2.as_u8());
+    }
+}
+
+extern "x86-interrupt" fn rtc_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    unsafe { rtc::acknowledge_interrupt() };
+    let h = &*HANDLERS.lock();
+    if let Some(handler) = h {
+        handler.handle_rtc();
+        if handler.has_event_loop() {
+            events::post(Event::Rtc);
+        }
+    }
+    unsafe {
+        PICS.lock()
+            .notify_end_of_interrupt(InterruptIndex::Rtc.as_u8());
+    }
+}
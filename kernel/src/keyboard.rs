@@ -0,0 +1,113 @@
+// Keyboard decoding for the keyboard interrupt handler.
+//
+// pc_keyboard makes the layout part of the Keyboard type, so each supported layout gets its own
+// variant of AnyKeyboard. Modifier keys are tracked here as well, since pc_keyboard 0.5 keeps its
+// own modifier state private.
+
+use lazy_static::lazy_static;
+use pc_keyboard::{layouts, DecodedKey, HandleControl, KeyCode, KeyEvent, KeyState, Keyboard, ScancodeSet1};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+pub use match3_core::input::{KeyboardLayout, Modifiers, RawKeyEvent};
+
+macro_rules! any_keyboard {
+    ($($layout:ident),*) => {
+        enum AnyKeyboard {
+            $($layout(Keyboard<layouts::$layout, ScancodeSet1>),)*
+        }
+
+        impl AnyKeyboard {
+            fn new(layout: KeyboardLayout) -> Self {
+                match layout {
+                    $(KeyboardLayout::$layout => AnyKeyboard::$layout(
+                        Keyboard::new(layouts::$layout, ScancodeSet1, HandleControl::Ignore)),)*
+                }
+            }
+
+            fn layout(&self) -> KeyboardLayout {
+                match self {
+                    $(AnyKeyboard::$layout(_) => KeyboardLayout::$layout,)*
+                }
+            }
+
+            fn add_byte(&mut self, scancode: u8) -> Option<KeyEvent> {
+                match self {
+                    $(AnyKeyboard::$layout(k) => k.add_byte(scancode).ok().flatten(),)*
+                }
+            }
+
+            fn process_keyevent(&mut self, event: KeyEvent) -> Option<DecodedKey> {
+                match self {
+                    $(AnyKeyboard::$layout(k) => k.process_keyevent(event),)*
+                }
+            }
+        }
+    }
+}
+
+any_keyboard!(Us104Key, Uk105Key, Jis109Key, Azerty, Dvorak104Key);
+
+struct KeyDecoder {
+    keyboard: AnyKeyboard,
+    modifiers: Modifiers,
+}
+
+lazy_static! {
+    static ref DECODER: Mutex<KeyDecoder> = Mutex::new(KeyDecoder {
+        keyboard: AnyKeyboard::new(KeyboardLayout::Us104Key),
+        modifiers: Modifiers::default(),
+    });
+}
+
+/// Switches the layout used to decode key presses. Modifier state carries over: the new
+/// Keyboard starts with nothing held, so the modifiers that are down are pressed on it again.
+pub fn set_layout(layout: KeyboardLayout) {
+    interrupts::without_interrupts(|| {
+        let decoder = &mut *DECODER.lock();
+        decoder.keyboard = AnyKeyboard::new(layout);
+        let m = decoder.modifiers;
+        let held = [
+            (m.lshift, KeyCode::ShiftLeft),
+            (m.rshift, KeyCode::ShiftRight),
+            (m.lctrl, KeyCode::ControlLeft),
+            (m.rctrl, KeyCode::ControlRight),
+            (m.lalt, KeyCode::AltLeft),
+            (m.ralt, KeyCode::AltRight),
+            (m.caps_lock, KeyCode::CapsLock),
+        ];
+        for (_, code) in held.iter().filter(|(down, _)| *down) {
+            decoder.keyboard.process_keyevent(KeyEvent::new(*code, KeyState::Down));
+        }
+    });
+}
+
+/// Returns the layout currently used to decode key presses.
+pub fn layout() -> KeyboardLayout {
+    interrupts::without_interrupts(|| DECODER.lock().keyboard.layout())
+}
+
+/// Feeds one scancode to the decoder. Once a complete key event has arrived, returns it, along
+/// with the decoded key if it was a key press that produces one.
+pub(crate) fn decode(scancode: u8) -> Option<(RawKeyEvent, Option<DecodedKey>)> {
+    let decoder = &mut *DECODER.lock();
+    let event = decoder.keyboard.add_byte(scancode)?;
+    update_modifiers(&mut decoder.modifiers, event.code, event.state);
+    let raw = RawKeyEvent { code: event.code, state: event.state, modifiers: decoder.modifiers };
+    Some((raw, decoder.keyboard.process_keyevent(event)))
+}
+
+/// Tracks the modifier keys and caps lock through one key event.
+fn update_modifiers(modifiers: &mut Modifiers, code: KeyCode, state: KeyState) {
+    let down = state == KeyState::Down;
+    match code {
+        KeyCode::ShiftLeft => modifiers.lshift = down,
+        KeyCode::ShiftRight => modifiers.rshift = down,
+        KeyCode::ControlLeft => modifiers.lctrl = down,
+        KeyCode::ControlRight => modifiers.rctrl = down,
+        KeyCode::AltLeft => modifiers.lalt = down,
+        KeyCode::AltRight => modifiers.ralt = down,
+        KeyCode::CapsLock if down => modifiers.caps_lock = !modifiers.caps_lock,
+        _ => {}
+    }
+}
@@ -0,0 +1,499 @@
+//! This crate enables the user to create a simple operating system by supplying interrupt handlers
+//! for the timer and the keyboard. As time and energy permits, I may add other interrupt handlers
+//! that seem useful.
+//!
+//! I developed this crate to support assignments in my operating systems course at Hendrix
+//! College. It provides a nice introduction to bare-metal programming. It has not been
+//! "battle-tested" in a production domain.
+//!
+//! The code is heavily derivative of the examples from the outstanding resource
+//! [Writing an Operating System in Rust](https://os.phil-opp.com/). I would like to gratefully
+//! acknowledge Philipp Oppermann's efforts to create this resource. Comments in each source file
+//! specify which code elements I have adopted from him.
+//!
+//! Before attempting to use this crate, it is essential to read the following tutorials. In fact,
+//! don't just read the tutorials; work through them! In particular, make sure to set up the
+//! [x86_64-blog_os.json](https://os.phil-opp.com/minimal-rust-kernel/) file and the
+//! [.cargo/config.toml](https://os.phil-opp.com/minimal-rust-kernel/) file as described therein.
+//! - [A Freestanding Rust Binary](https://os.phil-opp.com/freestanding-rust-binary/)
+//! - [A Minimal Rust Kernel](https://os.phil-opp.com/minimal-rust-kernel/)
+//! - [VGA Text Mode](https://os.phil-opp.com/vga-text-mode/)
+//! - [CPU Exceptions](https://os.phil-opp.com/cpu-exceptions/)
+//! - [Double Faults](https://os.phil-opp.com/double-fault-exceptions/)
+//! - [Hardware Interrupts](https://os.phil-opp.com/hardware-interrupts/)
+//!
+//! Having read and understood the ideas from the above tutorials, you can use this crate to create
+//! your own Pluggable Interrupt Operating System (PIOS).
+//!
+//! Here is a very basic example (found in main.rs in this crate):
+//! ```
+//! #![no_std]
+//! #![no_main]
+//!
+//! use pc_keyboard::DecodedKey;
+//! use pluggable_interrupt_os::HandlerTable;
+//!
+//! fn tick() {
+//!     print!(".");
+//! }
+//!
+//! fn key(key: DecodedKey) {
+//!     match key {
+//!         DecodedKey::Unicode(character) => print!("{}", character),
+//!         DecodedKey::RawKey(key) => print!("{:?}", key),
+//!     }
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn _start() -> ! {
+//!     HandlerTable::new()
+//!         .keyboard(key)
+//!         .timer(tick)
+//!         .start()
+//! }
+//! ```
+//!
+//! In this example, we begin with our interrupt handlers. The **tick()** handler prints a period
+//! on every timer event, and the **key()** handler displays the character typed whenever the
+//! key is pressed. The **_start()** function kicks everything off by placing references to these
+//! two functions in a **HandlerTable** object. Invoking **.start()** on the **HandlerTable**
+//! starts execution. The PIOS sits back and loops endlessly, relying on the event handlers to
+//! perform any events of interest or importance.
+//!
+//! As we can see from this example, the capabilities of your PIOS will be
+//! limited to handling keyboard events and displaying text in the VGA buffer. Within that scope,
+//! however, you can achieve quite a lot. I personally enjoyed recreating a version of a
+//! well-known 1980s [arcade classic](https://github.com/gjf2a/ghost_hunter).
+//!
+//! Here is the main.rs from that program:
+//! ```
+//! #![no_std]
+//! #![no_main]
+//!
+//! use lazy_static::lazy_static;
+//! use spin::Mutex;
+//! use ghost_hunter_core::GhostHunterGame;
+//! use ghost_hunter::MainGame;
+//! use pluggable_interrupt_os::HandlerTable;
+//! use pc_keyboard::DecodedKey;
+//!
+//! lazy_static! {
+//!     static ref GAME: Mutex<MainGame> = Mutex::new(GhostHunterGame::new());
+//! }
+//!
+//! fn tick() {
+//!     ghost_hunter::tick(&mut GAME.lock());
+//! }
+//!
+//! fn key(key: DecodedKey) {
+//!     GAME.lock().key(key);
+//! }
+//!
+//! #[no_mangle]
+//! pub extern "C" fn _start() -> ! {
+//!     HandlerTable::new()
+//!         .keyboard(key)
+//!         .timer(tick)
+//!         .start()
+//! }
+//! ```
+//!
+//! For this program, I created a
+//! [GhostHunterGame struct](https://github.com/gjf2a/ghost_hunter_core/blob/master/src/lib.rs)
+//! to represent the state of the game. It is wrapped in a **Mutex** and initialized using
+//! [lazy_static!](https://docs.rs/lazy_static/1.4.0/lazy_static/) to ensure safe access. Nearly
+//! any nontrivial program will need to make use of this design pattern.
+//!
+//! The **tick()** function calls a special
+//! [ghost_hunter::tick()](https://github.com/gjf2a/ghost_hunter/blob/master/src/lib.rs) function
+//! that handles details of drawing the game state in the VGA buffer. It also advances the ghosts
+//! by one position.
+//!
+//! The **key()** function calls the **GhostHunterGame::key()** method to convey updates to game
+//! state resulting from keypresses.
+//!
+//! This is a pedagogical experiment. I would be interested to hear from anyone who
+//! finds this useful or has suggestions.
+
+#![no_std]
+#![cfg_attr(test, no_main)]
+#![feature(abi_x86_interrupt)]
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+// hlt_loop() and init_memory() are Copyright (c) 2019 Philipp Oppermann.
+// Gabriel Ferrer wrote HandlerTable with its timer, keyboard, startup and cpu_loop handlers, and
+// start(). The remaining handlers, install(), the panic screen and the heap and task set-up were
+// added later.
+
+pub mod serial;
+pub mod vga_buffer;
+pub mod vga_graphics;
+pub mod vga_palette;
+pub mod interrupts;
+pub mod gdt;
+pub mod crash;
+pub mod testing;
+pub mod memory;
+pub mod allocator;
+pub mod task;
+pub mod events;
+pub mod keyboard;
+pub mod rtc;
+pub use match3_core::rng;
+pub mod entropy;
+
+use core::panic::PanicInfo;
+
+use pc_keyboard::DecodedKey;
+use x86_64::VirtAddr;
+use crash::PanicHook;
+use memory::BootInfoFrameAllocator;
+use task::{Executor, Task};
+use events::Event;
+use keyboard::{KeyboardLayout, RawKeyEvent};
+
+pub use bootloader::{entry_point, BootInfo};
+
+/// Table of interrupt handlers. This struct uses the
+/// [Builder pattern](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+/// Start by calling new() to create a new Handler table. Then use the appropriate methods to set
+/// up the handlers. When ready, call the **.start()** method to start up your pluggable
+/// interrupt operating system.
+///
+/// Handlers can be set for the timer, decoded keys, raw key events, the real-time clock and
+/// both serial ports, along with the keyboard layout. Instead of the cpu loop, the events can be
+/// handled by an event loop, and async tasks can be run.
+/// CPU exceptions are handled "behind the scenes": breakpoints are logged, and every other
+/// exception halts the system with a crash screen that is also sent to the serial port.
+pub struct HandlerTable {
+    timer: Option<fn()>,
+    keyboard: Option<fn(DecodedKey)>,
+    raw_keyboard: Option<fn(RawKeyEvent)>,
+    layout: KeyboardLayout,
+    rtc: Option<fn()>,
+    serial: Option<fn(u8)>,
+    serial2: Option<fn(u8)>,
+    startup: Option<fn()>,
+    panic_hook: Option<PanicHook>,
+    boot_info: Option<&'static BootInfo>,
+    tasks: Option<fn(&mut Executor)>,
+    event_handler: Option<fn(Event)>,
+    cpu_loop: fn() -> !
+}
+
+impl HandlerTable {
+    /// Creates a new HandlerTable with no handlers.
+    pub fn new() -> Self {
+        HandlerTable {timer: None, keyboard: None, raw_keyboard: None, layout: KeyboardLayout::Us104Key, rtc: None, serial: None, serial2: None, startup: None, panic_hook: None, boot_info: None, tasks: None, event_handler: None, cpu_loop: hlt_loop}
+    }
+
+    /// Starts up a simple operating system using the specified handlers.
+    pub fn start(self) -> ! {
+        let fore = self.cpu_loop;
+        let tasks = self.tasks;
+        let event_handler = self.event_handler;
+        self.install();
+        match (tasks, event_handler) {
+            (Some(spawn_tasks), _) => {
+                let mut executor = Executor::new();
+                if let Some(handler) = event_handler {
+                    executor.spawn(Task::new(events::dispatch_events(handler)));
+                }
+                spawn_tasks(&mut executor);
+                executor.run();
+            }
+            (None, Some(handler)) => events::run_event_loop(handler),
+            (None, None) => (fore)(),
+        }
+    }
+
+    /// Runs the startup handler, installs the handlers, and enables interrupts, but returns
+    /// instead of handing the CPU over to the cpu loop. **.start()** is usually what you want;
+    /// this is mainly useful for tests.
+    pub fn install(self) {
+        crash::set_panic_hook(self.panic_hook);
+        let boot_info = self.boot_info;
+        let startup = self.startup;
+        // the event loop hears from every source, whether or not it has a handler of its own
+        let events = self.has_event_loop();
+        let rtc = self.rtc.is_some() || events;
+        let serial = self.serial.is_some() || events;
+        let serial2 = self.serial2.is_some() || events;
+        keyboard::set_layout(self.layout);
+        // Exceptions can be reported from here on; hardware interrupts wait until the end.
+        gdt::init();
+        interrupts::init_idt(self);
+        if let Some(boot_info) = boot_info {
+            init_memory(boot_info);
+        }
+        startup.map(|f| f());
+        unsafe { interrupts::PICS.lock().initialize() };
+        if rtc {
+            interrupts::enable_rtc_interrupt();
+        }
+        if serial {
+            interrupts::enable_serial_interrupt();
+        }
+        if serial2 {
+            interrupts::enable_serial2_interrupt();
+        }
+        x86_64::instructions::interrupts::enable();
+    }
+
+    /// Sets the timer handler.
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn timer(mut self, timer_handler: fn()) -> Self {
+        self.timer = Some(timer_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a timer event.
+    pub fn handle_timer(&self) {
+        if let Some(timer) = self.timer {
+            (timer)()
+        }
+    }
+
+    /// Sets the keyboard handler. The [DecodedKey](https://docs.rs/pc-keyboard/0.5.1/pc_keyboard/enum.DecodedKey.html)
+    /// enum comes from the [pc_keyboard](https://crates.io/crates/pc-keyboard) crate.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn keyboard(mut self, keyboard_handler: fn(DecodedKey)) -> Self {
+        self.keyboard = Some(keyboard_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a keyboard event.
+    pub fn handle_keyboard(&self, key: DecodedKey) {
+        if let Some(keyboard) = self.keyboard {
+            (keyboard)(key)
+        }
+    }
+
+    /// Sets the raw keyboard handler. It sees every key going down or coming back up, including
+    /// keys such as Shift that never produce a **DecodedKey**, along with the modifier keys held
+    /// at the time. Holding a key down repeats its press event at the keyboard's own rate; a
+    /// program that wants its own repeat rate can instead track presses and releases.
+    ///
+    /// For a key press that decodes to a **DecodedKey**, this handler runs first, then the
+    /// keyboard handler.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn raw_keyboard(mut self, raw_keyboard_handler: fn(RawKeyEvent)) -> Self {
+        self.raw_keyboard = Some(raw_keyboard_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a raw key event.
+    pub fn handle_raw_keyboard(&self, event: RawKeyEvent) {
+        if let Some(raw_keyboard) = self.raw_keyboard {
+            (raw_keyboard)(event)
+        }
+    }
+
+    /// Sets the layout used to decode keys. The default is **KeyboardLayout::Us104Key**. The
+    /// layout can also be changed later with [keyboard::set_layout()](keyboard/fn.set_layout.html).
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn keyboard_layout(mut self, layout: KeyboardLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets the real-time clock handler. The clock's periodic interrupt is turned on and calls
+    /// it twice per second; [rtc::enable_periodic_interrupt()](rtc/fn.enable_periodic_interrupt.html)
+    /// can speed that up. The current date and time can be read at any point with
+    /// [rtc::read()](rtc/fn.read.html), with or without this handler.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn rtc(mut self, rtc_handler: fn()) -> Self {
+        self.rtc = Some(rtc_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a real-time clock event.
+    pub fn handle_rtc(&self) {
+        if let Some(rtc) = self.rtc {
+            (rtc)()
+        }
+    }
+
+    /// Sets the serial handler, which is called with each byte that arrives on
+    /// [SERIAL1](serial/struct.SERIAL1.html). With QEMU's `-serial stdio`, those are the keys
+    /// typed in the terminal QEMU was started from. Sending still works the same way with or
+    /// without this handler.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn serial(mut self, serial_handler: fn(u8)) -> Self {
+        self.serial = Some(serial_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a byte from the serial port.
+    pub fn handle_serial(&self, byte: u8) {
+        if let Some(serial) = self.serial {
+            (serial)(byte)
+        }
+    }
+
+    /// Sets the second serial handler, which is called with each byte that arrives on
+    /// [SERIAL2](serial/struct.SERIAL2.html). With QEMU's `-serial` given twice, the second one
+    /// decides where those come from, such as a socket to another QEMU. Send on it with
+    /// [serial::send_raw()](serial/fn.send_raw.html).
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn serial2(mut self, serial2_handler: fn(u8)) -> Self {
+        self.serial2 = Some(serial2_handler);
+        self
+    }
+
+    /// Called by the low-level interrupt routines to handle a byte from the second serial port.
+    pub fn handle_serial2(&self, byte: u8) {
+        if let Some(serial2) = self.serial2 {
+            (serial2)(byte)
+        }
+    }
+
+    /// Sets the startup handler.
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn startup(mut self, startup_handler: fn()) -> Self {
+        self.startup = Some(startup_handler);
+        self
+    }
+
+    /// Sets the panic hook. When the system panics, the panic screen shows the panic message and
+    /// location, then calls the hook so that it can write extra details, such as a dump of the
+    /// program's state. The hook's output appears on screen and is sent over the serial port.
+    ///
+    /// The hook runs after everything else has stopped, possibly while a lock it needs is still
+    /// held by the code that panicked, so it should avoid blocking on locks.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn panic_hook(mut self, panic_hook: PanicHook) -> Self {
+        self.panic_hook = Some(panic_hook);
+        self
+    }
+
+    /// Supplies the information the bootloader passes to the kernel entry point. With it,
+    /// **.start()** sets up paging and a kernel heap before calling the startup handler, so that
+    /// the [alloc](https://doc.rust-lang.org/alloc/) collections (**Vec**, **String**,
+    /// **BTreeMap**, ...) can be used. Without it, any heap allocation will panic.
+    ///
+    /// Use [entry_point!](https://docs.rs/bootloader/0.9.8/bootloader/macro.entry_point.html)
+    /// instead of defining **_start()** to receive the **BootInfo**:
+    /// ```
+    /// entry_point!(kernel_main);
+    ///
+    /// fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    ///     HandlerTable::new()
+    ///         .boot_info(boot_info)
+    ///         .start()
+    /// }
+    /// ```
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn boot_info(mut self, boot_info: &'static BootInfo) -> Self {
+        self.boot_info = Some(boot_info);
+        self
+    }
+
+    /// Sets the event handler. The interrupt handlers then just add an
+    /// [Event](events/enum.Event.html) to a queue, and instead of the cpu loop, **.start()** runs
+    /// a loop that passes each queued event to **event_handler**. Since the event handler never
+    /// runs inside an interrupt, it can take locks and do slow work, such as redrawing the
+    /// screen, without blocking interrupts or deadlocking against them.
+    ///
+    /// The event loop also turns on the real-time clock's periodic interrupt and the receive
+    /// interrupts of both serial ports, so **Event::Rtc**, **Event::Serial** and **Event::Serial2**
+    /// arrive without setting **.rtc()**, **.serial()** or **.serial2()**.
+    ///
+    /// If the event handler falls so far behind that the queue fills up, further events are
+    /// dropped; [events::overflow_count()](events/fn.overflow_count.html) reports how many.
+    ///
+    /// When **.tasks()** is also given, the events are handled by a task instead.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn event_loop(mut self, event_handler: fn(Event)) -> Self {
+        self.event_handler = Some(event_handler);
+        self
+    }
+
+    /// True if the interrupt handlers should queue events for the event loop.
+    pub(crate) fn has_event_loop(&self) -> bool {
+        self.event_handler.is_some()
+    }
+
+    /// Sets the task spawner. Instead of the cpu loop, **.start()** runs an async
+    /// [Executor](task/executor/struct.Executor.html), after first calling **spawn_tasks** to give
+    /// it the program's tasks. Tasks run outside of interrupt context, so they can take as long
+    /// as they need without delaying the handlers. They can wait for the timer with
+    /// [task::timer::sleep()](task/timer/fn.sleep.html) and read the keyboard with a
+    /// [ScancodeStream](task/keyboard/struct.ScancodeStream.html):
+    /// ```
+    /// async fn redraw() {
+    ///     loop {
+    ///         draw_everything();
+    ///         task::timer::sleep(1).await;
+    ///     }
+    /// }
+    ///
+    /// fn spawn_tasks(executor: &mut Executor) {
+    ///     executor.spawn(Task::new(redraw()));
+    /// }
+    ///
+    /// fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    ///     HandlerTable::new()
+    ///         .boot_info(boot_info)
+    ///         .tasks(spawn_tasks)
+    ///         .start()
+    /// }
+    /// ```
+    ///
+    /// Tasks live on the heap, so **.boot_info()** must also be supplied.
+    ///
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn tasks(mut self, spawn_tasks: fn(&mut Executor)) -> Self {
+        self.tasks = Some(spawn_tasks);
+        self
+    }
+
+    /// Sets the cpu loop handler.
+    /// This function should contain an infinite loop.
+    /// Returns Self for chained [Builder pattern construction](https://doc.rust-lang.org/1.0.0/style/ownership/builders.html).
+    pub fn cpu_loop(mut self, cpu_loop: fn() -> !) -> Self {
+        self.cpu_loop = cpu_loop;
+        self
+    }
+}
+
+fn init_memory(boot_info: &'static BootInfo) {
+    let phys_mem_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let mut mapper = unsafe { memory::init(phys_mem_offset) };
+    let mut frame_allocator = unsafe { BootInfoFrameAllocator::init(&boot_info.memory_map) };
+    allocator::init_heap(&mut mapper, &mut frame_allocator).expect("heap initialization failed");
+}
+
+fn hlt_loop() -> ! {
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    if testing::in_test_mode() {
+        testing::test_panic_handler(info);
+    }
+    crash::panic_screen(info);
+}
+
+#[cfg(test)]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    hlt_loop();
+}
\ No newline at end of file
@@ -0,0 +1,384 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{self, Write};
+use lazy_static::lazy_static;
+use match3_core::ansi::{AnsiMirror, Colors};
+use match3_core::draw::{self, cell_shift, FLASH_PERIOD, LOGO_COLORS};
+use match3_core::match3game::{self, GameState, GameStateManager, Outcome};
+use match3_core::screen::{ColorCode, Screen};
+use match3_core::terminal_keys::{self, Input};
+use pc_keyboard::DecodedKey;
+use csci320_match3::{entropy, entry_point, events, keyboard, rtc, BootInfo, HandlerTable, vga_buffer, vga_graphics, vga_palette};
+use csci320_match3::events::Event;
+use csci320_match3::serial::{self, SERIAL1};
+use csci320_match3::vga_graphics::Sprite;
+use spin::Mutex;
+use vga_buffer::{Color, WRITER};
+
+lazy_static! {
+    static ref TICK: Mutex<u64> = Mutex::new(0);
+}
+lazy_static! {
+    static ref GAME: Mutex<GameStateManager> = Mutex::new(GameStateManager::new(entropy::random_u64));
+}
+lazy_static! {
+    /// Pixel position of the graphical cursor, which glides toward the game cursor.
+    static ref CURSOR_PIXELS: Mutex<(usize, usize)> = Mutex::new((GFX_BOARD_X, GFX_BOARD_Y));
+}
+lazy_static! {
+    /// The terminal on the serial console, drawn apart from VGA memory so that the logo's colors
+    /// can rotate instead of relying on the palette. Basic colors keep each frame small.
+    static ref SERIAL_CONSOLE: Mutex<AnsiMirror> = Mutex::new(AnsiMirror::new(Colors::Basic));
+}
+lazy_static! {
+    static ref SERIAL_KEYS: Mutex<terminal_keys::Decoder> = Mutex::new(terminal_keys::Decoder::new());
+}
+
+// The logo is drawn in the dim colors, which are unused on the menu, and the bright gem colors
+// in draw::LOGO_COLORS are cycled through them.
+const LOGO_SLOTS: [Color; 7] = [
+    Color::Blue, Color::Green, Color::Cyan, Color::Red, Color::Magenta, Color::Brown, Color::LightGray
+];
+
+fn start() {
+    let gsm = &mut *GAME.lock();
+    vga_palette::apply_theme(gsm.get_settings().theme);
+    gsm.set_clock(rtc::read());
+}
+
+fn tick() {
+    *TICK.lock() += 1;
+    let gsm = &mut *GAME.lock();
+    let tick = *TICK.lock();
+    gsm.tick(tick);
+    SERIAL_KEYS.lock().tick(&mut |input| serial_input(gsm, input));
+    let output = gsm.get_settings().serial;
+    if output.serial() {
+        send_frame(gsm, tick);
+    }
+    if output.vga() {
+        draw_vga(gsm, tick);
+    }
+    let link_output = gsm.take_link_output();
+    if !link_output.is_empty() {
+        serial::send_raw(&link_output);
+    }
+}
+
+fn draw_vga(gsm: &GameStateManager, tick: u64) {
+    let screen = &mut *WRITER.lock();
+    match gsm.get_state() {
+        GameState::EnteringCode => {
+            if tick.is_multiple_of(FLASH_PERIOD) {
+                let palette = gsm.get_settings().theme.palette();
+                vga_palette::cycle(&palette, &LOGO_SLOTS, &LOGO_COLORS, (tick / FLASH_PERIOD) as usize);
+                draw::draw_logo(screen, &LOGO_SLOTS);
+            }
+            draw::draw_code_menu(screen, gsm);
+        },
+        GameState::Connecting => draw::draw_connecting(screen, gsm),
+        GameState::Playing => {
+            if vga_graphics::is_graphics_mode() {
+                draw_game_graphics(gsm, tick);
+            } else {
+                draw::draw_game(screen, gsm, tick);
+            }
+        },
+        GameState::Paused => draw::draw_pause_menu(screen, gsm),
+        GameState::Settings => draw::draw_settings_menu(screen, gsm)
+    }
+}
+
+fn handle_event(event: Event) {
+    match event {
+        Event::Tick => tick(),
+        Event::Key(k) => key(&mut GAME.lock(), k),
+        Event::RawKey(raw) => GAME.lock().raw_key(raw),
+        Event::Rtc => GAME.lock().set_clock(rtc::read()),
+        Event::Serial(byte) => {
+            let gsm = &mut *GAME.lock();
+            // frames go to the serial port, which is also the debug port, only once someone types
+            gsm.serial_console_seen();
+            SERIAL_KEYS.lock().push(byte, &mut |input| serial_input(gsm, input));
+        },
+        Event::Serial2(byte) => {
+            let gsm = &mut *GAME.lock();
+            let (old_state, old_settings) = (gsm.get_state(), gsm.get_settings());
+            // the versus game starts when the other kernel answers
+            gsm.link_byte(byte);
+            update_display(gsm, old_state, old_settings);
+        },
+    }
+}
+
+/// Sends the terminal on the serial console whatever changed since the last frame.
+fn send_frame(gsm: &GameStateManager, tick: u64) {
+    let console = &mut *SERIAL_CONSOLE.lock();
+    // the screens draw over whatever was there, as they do on VGA after it is cleared
+    console.clear_screen();
+    draw::draw_screen(console, gsm, tick);
+    let mut out = String::new();
+    // writing to a String cannot fail
+    let _ = console.flush(&mut out);
+    if !out.is_empty() {
+        let _ = SERIAL1.lock().write_str(&out);
+    }
+}
+
+/// Acts on a key typed on the serial console as if it had been typed on the keyboard.
+fn serial_input(gsm: &mut GameStateManager, input: Input) {
+    match input {
+        Input::Key(k) => key(gsm, k),
+        Input::Raw(raw) => gsm.raw_key(raw),
+        Input::Redraw => SERIAL_CONSOLE.lock().forget(),
+        // there is nothing to quit to
+        Input::Quit => {},
+    }
+}
+
+fn key(gsm: &mut GameStateManager, key: DecodedKey) {
+    let (old_state, old_settings) = (gsm.get_state(), gsm.get_settings());
+    gsm.key(key);
+    update_display(gsm, old_state, old_settings);
+}
+
+/// Switches the display mode, palette and keyboard layout to suit the game's new state and
+/// settings.
+fn update_display(gsm: &GameStateManager, old_state: GameState, old_settings: match3game::Settings) {
+    let new_state = gsm.get_state();
+    let settings = gsm.get_settings();
+    if new_state != old_state || settings.serial != old_settings.serial {
+        if new_state == GameState::Playing && settings.graphics && settings.serial.vga() {
+            vga_graphics::enter_mode_13h(&settings.theme.palette());
+        } else {
+            vga_graphics::enter_text_mode();
+            vga_buffer::clear_screen();
+            // undo any palette cycling from the logo
+            vga_palette::apply_theme(settings.theme);
+            if !settings.serial.vga() {
+                show_serial_notice();
+            }
+        }
+    } else if settings.theme != old_settings.theme {
+        vga_palette::apply_theme(settings.theme);
+    }
+    if settings.layout != old_settings.layout {
+        keyboard::set_layout(settings.layout);
+    }
+}
+
+/// Explains the blank VGA screen while the game is only drawn on the serial console.
+fn show_serial_notice() {
+    const NOTICE: &str = "The game is on the serial console. Change Serial in Settings to show it here.";
+    let screen = &mut *WRITER.lock();
+    let (width, height) = screen.size();
+    let col = (width - NOTICE.len()) / 2;
+    screen.plot_str(NOTICE, col, height / 2, ColorCode::new(Color::LightGray, Color::Black));
+}
+
+entry_point!(kernel_main);
+
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    #[cfg(test)]
+    test_main();
+
+    HandlerTable::new()
+        .boot_info(boot_info)
+        .event_loop(handle_event)
+        .startup(start)
+        .panic_hook(panic_report)
+        .start()
+}
+
+/// Adds the game state to the panic screen, so that a crash can be reproduced from its seed.
+fn panic_report(out: &mut dyn fmt::Write) -> fmt::Result {
+    // The panic probably happened inside tick() or key(), which will never release these now.
+    unsafe {
+        TICK.force_unlock();
+        GAME.force_unlock();
+    }
+    let gsm = GAME.lock();
+    let g = gsm.get_game();
+    writeln!(out, "Game state: {:?}   seed: {:#018x}   tick: {}   dropped events: {}",
+             gsm.get_state(), gsm.get_seed(), *TICK.lock(), events::overflow_count())?;
+    let (col, row) = g.get_cursor().location();
+    writeln!(out, "Score: {}   cursor: ({}, {})   selected: {}   alive: {}",
+             g.get_score() * 100, col, row, g.is_selected(), g.is_alive())?;
+    let board = g.get_board();
+    for row in 0..match3game::BOARD_HEIGHT {
+        write!(out, "  ")?;
+        for column in board.iter() {
+            match column[row] {
+                0 => write!(out, " .")?,
+                gem => write!(out, " {}", gem)?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// Graphical board layout: 24x24 pixel cells, centered horizontally.
+const GFX_CELL: usize = 24;
+const GFX_BOARD_X: usize = (vga_graphics::WIDTH - GFX_CELL * match3game::BOARD_WIDTH) / 2;
+const GFX_BOARD_Y: usize = (vga_graphics::HEIGHT - GFX_CELL * match3game::BOARD_HEIGHT) / 2;
+const GFX_CURSOR_SPEED: usize = 6;
+
+// Gem sprite: 1 = outline, 2 = body, 3 = shine, 4 = shadow
+const GEM_SIZE: usize = 20;
+const GEM_PIXELS: [u8; GEM_SIZE * GEM_SIZE] = sprite_from_art(concat!(
+    "......11111111......",
+    "....112222222211....",
+    "...12233322222221...",
+    "..1223332222222221..",
+    ".122332222222222221.",
+    ".122322222222222221.",
+    "12223222222222222221",
+    "12222222222222222221",
+    "12222222222222222221",
+    "12222222222222222221",
+    "12222222222222222221",
+    "12222222222222222221",
+    "12222222222222222241",
+    "12222222222222222441",
+    ".122222222222222441.",
+    ".122222222222224441.",
+    "..1222222222224441..",
+    "...12222222444441...",
+    "....114444444411....",
+    "......11111111......",
+));
+const GEM_SPRITE: Sprite = Sprite { width: GEM_SIZE, height: GEM_SIZE, pixels: &GEM_PIXELS };
+
+/// Converts ASCII art into sprite pixels: '.' becomes 0 and each digit becomes its value.
+const fn sprite_from_art(art: &str) -> [u8; GEM_SIZE * GEM_SIZE] {
+    let bytes = art.as_bytes();
+    let mut pixels = [0; GEM_SIZE * GEM_SIZE];
+    let mut i = 0;
+    while i < pixels.len() {
+        pixels[i] = if bytes[i] == b'.' { 0 } else { bytes[i] - b'0' };
+        i += 1;
+    }
+    pixels
+}
+
+fn draw_game_graphics(gsm: &GameStateManager, tick: u64) {
+    const SELECT_BLINK_PERIOD: u64 = 4;
+    const TEXT_X: usize = 4;
+    let g = gsm.get_game();
+    let board = g.get_board();
+    vga_graphics::clear(Color::Black as u8);
+    // gems
+    for (col, column) in board.iter().enumerate() {
+        for (row, &current) in column.iter().enumerate() {
+            if current == 0 { continue; }
+            let body = current + if g.is_alive() { 8 } else { 0 };
+            let blink = g.get_cursor().location() == (col, row)
+                && g.is_selected()
+                && tick % (SELECT_BLINK_PERIOD * 2) < SELECT_BLINK_PERIOD;
+            let shine = if blink { body } else { Color::White as u8 };
+            let color_map = match g.get_clearing(col, row) {
+                Some(ticks_left) if ticks_left % 2 == 0 => [vga_graphics::TRANSPARENT, 15, 15, 15, 15],
+                _ if current == match3game::STONE => {
+                    let (light, dark) = (Color::LightGray as u8, Color::DarkGray as u8);
+                    [vga_graphics::TRANSPARENT, dark, light, light, dark]
+                },
+                _ => [vga_graphics::TRANSPARENT, current, body, shine, current]
+            };
+            let (dx, dy) = g.get_offset(col, row);
+            let x = (GFX_BOARD_X + col * GFX_CELL + (GFX_CELL - GEM_SIZE) / 2) as isize
+                + cell_shift(dx, GFX_CELL as isize);
+            let y = (GFX_BOARD_Y + row * GFX_CELL + (GFX_CELL - GEM_SIZE) / 2) as isize
+                + cell_shift(dy, GFX_CELL as isize);
+            vga_graphics::blit(&GEM_SPRITE, x, y, &color_map);
+        }
+    }
+    // hide gems that are still falling in from above the board
+    vga_graphics::fill_rect(0, 0, vga_graphics::WIDTH, GFX_BOARD_Y, Color::Black as u8);
+    // score popups
+    for popup in g.get_popups() {
+        let points = popup.points as isize * 100;
+        let width = (vga_buffer::num_str_len(points) + 1) * vga_graphics::FONT_WIDTH;
+        let x = (GFX_BOARD_X + popup.center.0 * GFX_CELL / 2).saturating_sub(width / 2);
+        let rise = popup.age as usize * 2 + vga_graphics::FONT_HEIGHT / 2;
+        let y = (GFX_BOARD_Y + popup.center.1 * GFX_CELL / 2).saturating_sub(rise).max(GFX_BOARD_Y);
+        let x = vga_graphics::draw_str("+", x, y, Color::Yellow as u8, vga_graphics::TRANSPARENT);
+        vga_graphics::draw_num(points, x, y, Color::Yellow as u8, vga_graphics::TRANSPARENT);
+    }
+    // cursor glides a few pixels per frame toward the selected cell
+    let (cursor_col, cursor_row) = g.get_cursor().location();
+    let target = (GFX_BOARD_X + cursor_col * GFX_CELL, GFX_BOARD_Y + cursor_row * GFX_CELL);
+    let cursor = &mut *CURSOR_PIXELS.lock();
+    cursor.0 = approach(cursor.0, target.0, GFX_CURSOR_SPEED);
+    cursor.1 = approach(cursor.1, target.1, GFX_CURSOR_SPEED);
+    let cursor_color = if g.is_selected() {
+        Color::Yellow
+    } else if tick % (SELECT_BLINK_PERIOD * 2) < SELECT_BLINK_PERIOD {
+        Color::LightGray
+    } else {
+        Color::White
+    };
+    vga_graphics::draw_rect(cursor.0, cursor.1, GFX_CELL, GFX_CELL, 2, cursor_color as u8);
+    // frame and score
+    vga_graphics::draw_rect(
+        GFX_BOARD_X - 2, GFX_BOARD_Y - 2,
+        GFX_CELL * match3game::BOARD_WIDTH + 4, GFX_CELL * match3game::BOARD_HEIGHT + 4,
+        1, Color::DarkGray as u8
+    );
+    let text = Color::White as u8;
+    let background = vga_graphics::TRANSPARENT;
+    vga_graphics::draw_str("Score", TEXT_X, GFX_BOARD_Y, text, background);
+    vga_graphics::draw_num(g.get_score() as isize * 100, TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT, text, background);
+    if let Some(best) = gsm.get_high_score() {
+        vga_graphics::draw_str("Best", TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 6, text, background);
+        vga_graphics::draw_num(best.score as isize, TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 7, text, background);
+    }
+    // the right side only has room for 7 characters
+    let right_x = GFX_BOARD_X + GFX_CELL * match3game::BOARD_WIDTH + TEXT_X;
+    if let Some(now) = gsm.get_clock() {
+        let time = format!("{:02}:{:02}", now.hour, now.minute);
+        vga_graphics::draw_str(&time, right_x, GFX_BOARD_Y, text, background);
+    }
+    // seed code without its prefix, four digits per line
+    let seed_code = gsm.get_seed_code();
+    vga_graphics::draw_str("Seed", right_x, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 2, text, background);
+    for (i, chunk) in seed_code.as_bytes()[1..].chunks(4).enumerate() {
+        let chunk = core::str::from_utf8(chunk).unwrap_or("");
+        vga_graphics::draw_str(chunk, right_x, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * (3 + i), text, background);
+    }
+    if !g.is_alive() {
+        vga_graphics::draw_str("Game", TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 3, text, background);
+        vga_graphics::draw_str("Over!", TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 4, text, background);
+    }
+    if let Some(versus) = gsm.get_versus() {
+        vga_graphics::draw_str("Rival", TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 9, text, background);
+        vga_graphics::draw_num(versus.opponent_score as isize * 100, TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 10, text, background);
+        let result = match versus.outcome {
+            Some(Outcome::Won) => "Win!",
+            Some(Outcome::Lost) => "Lost",
+            None => "",
+        };
+        if versus.opponent_gone {
+            vga_graphics::draw_str("Gone", TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 11, text, background);
+        }
+        vga_graphics::draw_str(result, TEXT_X, GFX_BOARD_Y + vga_graphics::FONT_HEIGHT * 12, text, background);
+    }
+    vga_graphics::present();
+}
+
+/// Moves **current** toward **target** by at most **step**.
+fn approach(current: usize, target: usize, step: usize) -> usize {
+    if current < target {
+        target.min(current + step)
+    } else {
+        target.max(current.saturating_sub(step))
+    }
+}
@@ -0,0 +1,160 @@
+// CMOS real-time clock.
+//
+// The RTC keeps the date and time in a handful of CMOS registers, reached through an index port
+// and a data port. Depending on status register B, the values are either binary or BCD, and the
+// hour is either 24-hour or 12-hour with a PM flag in its top bit. The same chip can also raise
+// a periodic interrupt on IRQ8.
+//
+// Register details come from the OSDev wiki: https://wiki.osdev.org/CMOS and
+// https://wiki.osdev.org/RTC
+
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+pub use match3_core::date::{Date, DateTime};
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+/// Setting this bit of the index disables non-maskable interrupts; it is left clear.
+const NMI_DISABLE: u8 = 0x80;
+
+const SECONDS: u8 = 0x00;
+const MINUTES: u8 = 0x02;
+const HOURS: u8 = 0x04;
+const DAY: u8 = 0x07;
+const MONTH: u8 = 0x08;
+const YEAR: u8 = 0x09;
+/// Not standard, but QEMU and most PC firmware keep the century here.
+const CENTURY: u8 = 0x32;
+const STATUS_A: u8 = 0x0A;
+const STATUS_B: u8 = 0x0B;
+const STATUS_C: u8 = 0x0C;
+
+const UPDATE_IN_PROGRESS: u8 = 0x80;
+const PERIODIC_INTERRUPT: u8 = 0x40;
+const BINARY_MODE: u8 = 0x04;
+const HOUR_24: u8 = 0x02;
+const PM: u8 = 0x80;
+
+/// The slowest periodic interrupt rate, which fires twice per second. The interrupt frequency
+/// is 32768 >> (rate - 1) Hz, for rates from 3 (8192 Hz) up to this.
+pub const SLOWEST_RATE: u8 = 15;
+const FASTEST_RATE: u8 = 3;
+
+/// The raw register values, before BCD and 12-hour decoding.
+#[derive(PartialEq, Eq)]
+struct Registers {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+unsafe fn read_register(register: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS);
+    let mut data: Port<u8> = Port::new(CMOS_DATA);
+    address.write(register & !NMI_DISABLE);
+    data.read()
+}
+
+unsafe fn write_register(register: u8, value: u8) {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS);
+    let mut data: Port<u8> = Port::new(CMOS_DATA);
+    address.write(register & !NMI_DISABLE);
+    data.write(value);
+}
+
+unsafe fn read_registers() -> Registers {
+    while read_register(STATUS_A) & UPDATE_IN_PROGRESS != 0 {}
+    Registers {
+        second: read_register(SECONDS),
+        minute: read_register(MINUTES),
+        hour: read_register(HOURS),
+        day: read_register(DAY),
+        month: read_register(MONTH),
+        year: read_register(YEAR),
+        century: read_register(CENTURY),
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Reads the current date and time.
+pub fn read() -> DateTime {
+    let (registers, status_b) = interrupts::without_interrupts(|| unsafe {
+        // An update can begin between reads, so read until two passes agree.
+        let mut registers = read_registers();
+        loop {
+            let again = read_registers();
+            if again == registers {
+                break;
+            }
+            registers = again;
+        }
+        (registers, read_register(STATUS_B))
+    });
+    decode(registers, status_b)
+}
+
+fn decode(registers: Registers, status_b: u8) -> DateTime {
+    let binary = status_b & BINARY_MODE != 0;
+    let convert = |value: u8| if binary { value } else { from_bcd(value) };
+    let pm = registers.hour & PM != 0;
+    let mut hour = convert(registers.hour & !PM);
+    if status_b & HOUR_24 == 0 {
+        // 12 AM is midnight and 12 PM is noon
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+    let century = match convert(registers.century) {
+        century @ 19..=21 => century,
+        _ => 20,
+    };
+    DateTime {
+        date: Date {
+            year: century as u16 * 100 + convert(registers.year) as u16,
+            month: convert(registers.month),
+            day: convert(registers.day),
+        },
+        hour,
+        minute: convert(registers.minute),
+        second: convert(registers.second),
+    }
+}
+
+/// Turns on the periodic interrupt at **rate**, which is clamped to the range 3 to
+/// [SLOWEST_RATE]. [HandlerTable::rtc()](../struct.HandlerTable.html#method.rtc) does this for
+/// you at the slowest rate; call this afterwards to change it.
+pub fn enable_periodic_interrupt(rate: u8) {
+    let rate = rate.clamp(FASTEST_RATE, SLOWEST_RATE);
+    interrupts::without_interrupts(|| unsafe {
+        let a = read_register(STATUS_A);
+        write_register(STATUS_A, (a & 0xF0) | rate);
+        let b = read_register(STATUS_B);
+        write_register(STATUS_B, b | PERIODIC_INTERRUPT);
+        acknowledge_interrupt();
+    });
+}
+
+/// Turns off the periodic interrupt.
+pub fn disable_periodic_interrupt() {
+    interrupts::without_interrupts(|| unsafe {
+        let b = read_register(STATUS_B);
+        write_register(STATUS_B, b & !PERIODIC_INTERRUPT);
+    });
+}
+
+/// Reading status register C acknowledges the interrupt; until then, the RTC raises no more.
+///
+/// # Safety
+/// Interrupts must be disabled, so that nothing else uses the CMOS index port in between.
+pub(crate) unsafe fn acknowledge_interrupt() {
+    read_register(STATUS_C);
+}
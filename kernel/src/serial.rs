@@ -0,0 +1,97 @@
+// Code in this file is largely Copyright (c) 2019 Philipp Oppermann.
+// SERIAL2, receive(), send_raw() and the port constants were added for reading from the serial
+// ports and for a second port that carries the versus link.
+
+use uart_16550::SerialPort;
+use spin::Mutex;
+use lazy_static::lazy_static;
+use x86_64::instructions::port::Port;
+
+pub const COM1: u16 = 0x3F8;
+pub const COM2: u16 = 0x2F8;
+/// COM1's line on the primary PIC (IRQ 4).
+pub(crate) const COM1_IRQ: u8 = 4;
+/// COM2's line on the primary PIC (IRQ 3).
+pub(crate) const COM2_IRQ: u8 = 3;
+/// Offset of the line status register from a port's base.
+const LINE_STATUS: u16 = 5;
+/// Set in the line status register when a received byte is waiting.
+const DATA_READY: u8 = 1;
+/// Set in the line status register when the port can take another byte to send.
+const TRANSMIT_EMPTY: u8 = 1 << 5;
+
+lazy_static! {
+    pub static ref SERIAL1: Mutex<SerialPort> = {
+        let mut serial_port = unsafe { SerialPort::new(COM1) };
+        serial_port.init();
+        Mutex::new(serial_port)
+    };
+}
+
+lazy_static! {
+    /// The second serial port, kept apart from SERIAL1 so that debug output never gets mixed
+    /// into what is sent on it.
+    pub static ref SERIAL2: Mutex<SerialPort> = {
+        let mut serial_port = unsafe { SerialPort::new(COM2) };
+        serial_port.init();
+        Mutex::new(serial_port)
+    };
+}
+
+/// Returns the next byte received on the serial port at **port**, if one is waiting.
+///
+/// This reads the port's registers directly instead of locking SERIAL1 or SERIAL2, so that the
+/// interrupt handler can call it while other code is in the middle of sending. The port must
+/// have been initialized first, since that is what sets its speed.
+pub(crate) fn receive(port: u16) -> Option<u8> {
+    let mut line_status: Port<u8> = Port::new(port + LINE_STATUS);
+    let mut data: Port<u8> = Port::new(port);
+    unsafe {
+        if line_status.read() & DATA_READY == 0 {
+            return None;
+        }
+        Some(data.read())
+    }
+}
+
+/// Sends **bytes** on SERIAL2 exactly as they are.
+///
+/// SerialPort::send() turns backspace and delete into the bytes that erase a character on a
+/// terminal, which would corrupt binary data, so this writes the port's registers itself. It
+/// holds the lock on SERIAL2 meanwhile, which also initializes the port if need be.
+pub fn send_raw(bytes: &[u8]) {
+    let _port = SERIAL2.lock();
+    let mut line_status: Port<u8> = Port::new(COM2 + LINE_STATUS);
+    let mut data: Port<u8> = Port::new(COM2);
+    for byte in bytes {
+        unsafe {
+            while line_status.read() & TRANSMIT_EMPTY == 0 {
+                core::hint::spin_loop();
+            }
+            data.write(*byte);
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: ::core::fmt::Arguments) {
+    use core::fmt::Write;
+    SERIAL1.lock().write_fmt(args).expect("Printing to serial failed");
+}
+
+/// Prints to the host through the serial interface.
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => {
+        $crate::serial::_print(format_args!($($arg)*));
+    };
+}
+
+/// Prints to the host through the serial interface, appending a newline.
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($fmt:expr) => ($crate::serial_print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::serial_print!(
+        concat!($fmt, "\n"), $($arg)*));
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+// Adapted from https://os.phil-opp.com/async-await/#async-keyboard-input
+// Copyright (c) 2020 Philipp Oppermann.
+// add_scancode() was changed to ignore scancodes until a stream exists, since the
+// keyboard handler feeds it whether or not any task is listening.
+
+use conquer_once::spin::OnceCell;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use crossbeam_queue::ArrayQueue;
+use futures_util::stream::Stream;
+use futures_util::task::AtomicWaker;
+
+const SCANCODE_QUEUE_SIZE: usize = 100;
+
+static SCANCODE_QUEUE: OnceCell<ArrayQueue<u8>> = OnceCell::uninit();
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Called by the keyboard interrupt handler
+///
+/// Must not block or allocate.
+pub(crate) fn add_scancode(scancode: u8) {
+    if let Ok(queue) = SCANCODE_QUEUE.try_get() {
+        if queue.push(scancode).is_err() {
+            crate::serial_println!("WARNING: scancode queue full; dropping keyboard input");
+        } else {
+            WAKER.wake();
+        }
+    }
+}
+
+/// A stream of raw scancodes from the keyboard, in scancode set 1. Decode them with a
+/// [pc_keyboard::Keyboard](https://docs.rs/pc-keyboard/0.5.1/pc_keyboard/struct.Keyboard.html):
+/// ```
+/// async fn print_keypresses() {
+///     let mut scancodes = ScancodeStream::new();
+///     let mut keyboard = Keyboard::new(layouts::Us104Key, ScancodeSet1, HandleControl::Ignore);
+///     while let Some(scancode) = scancodes.next().await {
+///         if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
+///             if let Some(key) = keyboard.process_keyevent(key_event) {
+///                 println!("{:?}", key);
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// Scancodes are only collected once a stream exists, and only one stream may be created.
+pub struct ScancodeStream {
+    _private: (),
+}
+
+impl ScancodeStream {
+    pub fn new() -> Self {
+        SCANCODE_QUEUE
+            .try_init_once(|| ArrayQueue::new(SCANCODE_QUEUE_SIZE))
+            .expect("ScancodeStream::new should only be called once");
+        ScancodeStream { _private: () }
+    }
+}
+
+impl Default for ScancodeStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stream for ScancodeStream {
+    type Item = u8;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u8>> {
+        let queue = SCANCODE_QUEUE
+            .try_get()
+            .expect("scancode queue not initialized");
+
+        // fast path
+        if let Some(scancode) = queue.pop() {
+            return Poll::Ready(Some(scancode));
+        }
+
+        WAKER.register(cx.waker());
+        match queue.pop() {
+            Some(scancode) => {
+                WAKER.take();
+                Poll::Ready(Some(scancode))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
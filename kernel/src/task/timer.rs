@@ -0,0 +1,103 @@
+// Timer ticks for async tasks.
+//
+// The timer interrupt handler counts ticks and wakes every sleeper whose deadline has passed.
+// Sleepers are only added and removed with interrupts disabled, and the handler only wakes them
+// by reference, so the interrupt handler never allocates or frees memory.
+
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static SLEEPERS: Mutex<Vec<Sleeper>> = Mutex::new(Vec::new());
+
+struct Sleeper {
+    id: u64,
+    deadline: u64,
+    waker: Waker,
+}
+
+/// Called by the timer interrupt handler.
+pub(crate) fn tick() {
+    let now = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    // A task only holds the lock with interrupts disabled, so it is always free here.
+    if let Some(sleepers) = SLEEPERS.try_lock() {
+        for sleeper in sleepers.iter().filter(|s| s.deadline <= now) {
+            sleeper.waker.wake_by_ref();
+        }
+    }
+}
+
+/// Returns the number of timer interrupts since the system started.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Returns a future that completes after **ticks** more timer interrupts. The timer fires about
+/// 18.2 times per second.
+pub fn sleep(ticks: u64) -> Sleep {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    Sleep {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        deadline: self::ticks() + ticks,
+    }
+}
+
+/// The future returned by [sleep].
+pub struct Sleep {
+    id: u64,
+    deadline: u64,
+}
+
+impl Sleep {
+    fn is_due(&self) -> bool {
+        ticks() >= self.deadline
+    }
+
+    fn register(&self, waker: &Waker) {
+        interrupts::without_interrupts(|| {
+            let mut sleepers = SLEEPERS.lock();
+            match sleepers.iter_mut().find(|s| s.id == self.id) {
+                Some(sleeper) => {
+                    if !sleeper.waker.will_wake(waker) {
+                        sleeper.waker = waker.clone();
+                    }
+                }
+                None => sleepers.push(Sleeper { id: self.id, deadline: self.deadline, waker: waker.clone() }),
+            }
+        });
+    }
+
+    fn unregister(&self) {
+        interrupts::without_interrupts(|| SLEEPERS.lock().retain(|s| s.id != self.id));
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.is_due() {
+            self.unregister();
+            return Poll::Ready(());
+        }
+        self.register(cx.waker());
+        // The deadline may have passed before the waker was registered.
+        if self.is_due() {
+            self.unregister();
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
@@ -0,0 +1,76 @@
+// Support for running tests inside QEMU, since the standard test harness needs std.
+//
+// test_runner(), exit_qemu() and the Testable trait are Copyright (c) 2019 Philipp Oppermann,
+// from https://os.phil-opp.com/testing/
+//
+// Test results are reported over SERIAL1, and QEMU is shut down through its isa-debug-exit device
+// with an exit code that says whether every test passed. See [package.metadata.bootimage] in
+// Cargo.toml for the matching QEMU arguments.
+
+use core::any::type_name;
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use x86_64::instructions::port::Port;
+use crate::{serial_print, serial_println};
+
+const ISA_DEBUG_EXIT_PORT: u16 = 0xf4;
+
+static TEST_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Values written to the isa-debug-exit device. QEMU exits with status `(code << 1) | 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum QemuExitCode {
+    Success = 0x10,
+    Failed = 0x11,
+}
+
+/// Shuts down QEMU with the given exit code.
+pub fn exit_qemu(exit_code: QemuExitCode) {
+    let mut port: Port<u32> = Port::new(ISA_DEBUG_EXIT_PORT);
+    unsafe { port.write(exit_code as u32); }
+}
+
+/// A test that reports its own name and result over serial.
+pub trait Testable {
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        serial_print!("{}...\t", type_name::<T>());
+        self();
+        serial_println!("[ok]");
+    }
+}
+
+/// Runs every `#[test_case]` in order and then exits QEMU with success. A failing test panics,
+/// which ends the run through test_panic_handler().
+///
+/// Select it in a test crate with `#![test_runner(csci320_match3::testing::test_runner)]`.
+pub fn test_runner(tests: &[&dyn Testable]) {
+    enter_test_mode();
+    serial_println!("Running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    exit_qemu(QemuExitCode::Success);
+}
+
+/// Makes panics report a test failure and exit QEMU, instead of showing the panic screen.
+/// test_runner() calls this; tests that use `harness = false` should call it themselves.
+pub fn enter_test_mode() {
+    TEST_MODE.store(true, Ordering::SeqCst);
+}
+
+pub(crate) fn in_test_mode() -> bool {
+    TEST_MODE.load(Ordering::SeqCst)
+}
+
+/// Reports a failed test over serial and exits QEMU with a failure code.
+pub fn test_panic_handler(info: &PanicInfo) -> ! {
+    serial_println!("[failed]\n");
+    serial_println!("Error: {}\n", info);
+    exit_qemu(QemuExitCode::Failed);
+    crate::hlt_loop();
+}
@@ -7,11 +7,17 @@
 // - ColorCode::{foreground(), background()}
 // - Plot enum
 // - impl From for Color
+//
+// Color, ColorCode and the bodies of the drawing functions now live in match3-core's screen.rs,
+// where Writer implements its Screen trait. The functions here draw on WRITER through it.
 
 use volatile::Volatile;
 use core::fmt;
 use lazy_static::lazy_static;
 use spin::Mutex;
+use match3_core::screen::Screen;
+
+pub use match3_core::screen::{Color, ColorCode, BUFFER_HEIGHT, BUFFER_WIDTH, num_str_len};
 
 const MIN_DRAWABLE: u8 = 0x20;
 const MAX_DRAWABLE: u8 = 0x7e;
@@ -24,62 +30,6 @@ lazy_static! {
     });
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(u8)]
-/// Represents a 4-bit x86 color code
-pub enum Color {
-    Black = 0,
-    Blue = 1,
-    Green = 2,
-    Cyan = 3,
-    Red = 4,
-    Magenta = 5,
-    Brown = 6,
-    LightGray = 7,
-    DarkGray = 8,
-    LightBlue = 9,
-    LightGreen = 10,
-    LightCyan = 11,
-    LightRed = 12,
-    Pink = 13,
-    Yellow = 14,
-    White = 15,
-}
-
-impl From<u8> for Color {
-    // I attempted to use the enum-repr crate instead of doing this, but it did not compile.
-    fn from(n: u8) -> Self {
-        use Color::*;
-        match n {
-            0 => Black, 1 => Blue, 2 => Green, 3 => Cyan, 4 => Red, 5 => Magenta, 6 => Brown,
-            7 => LightGray, 8 => DarkGray, 9 => LightBlue, 10 => LightGreen, 11 => LightCyan,
-            12 => LightRed, 13 => Pink, 14 => Yellow, 15 => White,
-            _ => panic!("Undefined color value: {}", n)
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(transparent)]
-/// Represents two 4-bit x86 colors: a foreground and a background
-pub struct ColorCode(u8);
-
-#[allow(dead_code)]
-impl ColorCode {
-    pub fn new(foreground: Color, background: Color) -> ColorCode {
-        ColorCode((background as u8) << 4 | (foreground as u8))
-    }
-
-    pub fn foreground(&self) -> Color {
-        Color::from(self.0 & 0xF)
-    }
-
-    pub fn background(&self) -> Color {
-        Color::from((self.0 & 0xF0) >> 4)
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 struct ScreenChar {
@@ -87,9 +37,6 @@ struct ScreenChar {
     color_code: ColorCode,
 }
 
-pub const BUFFER_HEIGHT: usize = 25;
-pub const BUFFER_WIDTH: usize = 80;
-
 #[repr(transparent)]
 struct Buffer {
     chars: [[Volatile<ScreenChar>; BUFFER_WIDTH]; BUFFER_HEIGHT],
@@ -180,6 +127,21 @@ impl fmt::Write for Writer {
     }
 }
 
+impl Screen for Writer {
+    fn size(&self) -> (usize, usize) {
+        (BUFFER_WIDTH, BUFFER_HEIGHT)
+    }
+
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        Writer::plot(self, col, row, ScreenChar { ascii_character: c as u8, color_code: color });
+    }
+
+    fn peek(&self, col: usize, row: usize) -> (char, ColorCode) {
+        let result = Writer::peek(self, col, row);
+        (result.ascii_character as char, result.color_code)
+    }
+}
+
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => ($crate::vga_buffer::_print(format_args!($($arg)*)));
@@ -205,18 +167,13 @@ pub fn _print(args: fmt::Arguments) {
 /// Clears one row of the VGA buffer, setting everything to the background color specified.
 /// It will **panic** on an illegal row.
 pub fn clear_row(row: usize, background: Color) {
-    let color = ColorCode::new(background, background);
-    for col in 0..BUFFER_WIDTH {
-        plot(' ', col, row, color);
-    }
+    Screen::clear_row(&mut *WRITER.lock(), row, background)
 }
 
 #[allow(dead_code)]
 /// Sets all rows of the VGA buffer to Black.
 pub fn clear_screen() {
-    for row in 0..BUFFER_HEIGHT {
-        clear_row(row, Color::Black);
-    }
+    WRITER.lock().clear_screen()
 }
 
 #[allow(dead_code)]
@@ -224,13 +181,7 @@ pub fn clear_screen() {
 /// If the string exceeds the width of the buffer, it will be truncated.
 /// An illegal row will **panic**.
 pub fn plot_str(s: &str, col: usize, row: usize, color: ColorCode) -> usize {
-    use crate::serial_println;
-    let end = BUFFER_WIDTH.min(col + s.len());
-    for (c, chr) in (col..end).zip(s.chars()) {
-        serial_println!("Plotting {} ({},{})", chr, c, row);
-        plot(chr, c, row, color);
-    }
-    end % BUFFER_WIDTH
+    WRITER.lock().plot_str(s, col, row, color)
 }
 
 #[allow(dead_code)]
@@ -238,36 +189,14 @@ pub fn plot_str(s: &str, col: usize, row: usize, color: ColorCode) -> usize {
 /// Returns the next column to use after the call.
 /// It will **panic** on an illegal row.
 pub fn clear(num_spaces: usize, col: usize, row: usize, color: ColorCode) -> usize {
-    let end = BUFFER_WIDTH.min(col + num_spaces);
-    for c in col..end {
-        plot(' ', c, row, color);
-    }
-    end % BUFFER_WIDTH
+    WRITER.lock().clear(num_spaces, col, row, color)
 }
 
 #[allow(dead_code)]
 /// Plots the given character at the given location with the given color.
 /// It will **panic** on an illegal row or column.
 pub fn plot(c: char, col: usize, row: usize, color: ColorCode) {
-    WRITER.lock().plot(col, row, ScreenChar { ascii_character: c as u8, color_code: color });
-}
-
-#[allow(dead_code)]
-/// Returns the length **num** would have when plotted.
-pub fn num_str_len(num: isize) -> usize {
-    if num == 0 {
-        1
-    } else if num < 0 {
-        1 + num_str_len(-num)
-    } else {
-        let mut num = num;
-        let mut c = 0;
-        while num > 0 {
-            num /= 10;
-            c += 1;
-        }
-        c
-    }
+    Screen::plot(&mut *WRITER.lock(), c, col, row, color)
 }
 
 #[allow(dead_code)]
@@ -282,12 +211,7 @@ pub fn num_str_len(num: isize) -> usize {
 ///
 /// It will **panic** if an illegal row is given.
 pub fn plot_num_right_justified(total_space: usize, num: isize, col: usize, row: usize, color: ColorCode) -> usize {
-    let space_needed = num_str_len(num);
-    let leading_spaces = if space_needed < total_space {total_space - space_needed} else {0};
-    if leading_spaces > 0 {
-        clear(leading_spaces, col, row, ColorCode::new(color.background(), color.background()));
-    }
-    plot_num(num, col + leading_spaces, row, color)
+    WRITER.lock().plot_num_right_justified(total_space, num, col, row, color)
 }
 
 #[allow(dead_code)]
@@ -298,26 +222,7 @@ pub fn plot_num_right_justified(total_space: usize, num: isize, col: usize, row:
 ///
 /// It will **panic** if an illegal row is given.
 pub fn plot_num(num: isize, col: usize, row: usize, color: ColorCode) -> usize {
-    if num == 0 {
-        plot('0', col, row, color);
-        (col + 1) % BUFFER_WIDTH
-    } else if num < 0 {
-        plot('-', col, row, color);
-        plot_num(-num, col + 1, row, color)
-    } else {
-        let mut buffer = [' '; BUFFER_WIDTH];
-        let mut c = 0;
-        let mut num = num;
-        while num > 0 && c + col < buffer.len() {
-            buffer[c] = ((num % 10 + '0' as isize) as u8) as char;
-            num /= 10;
-            c += 1;
-        }
-        for i in 0..c {
-            plot(buffer[i], col + c - i - 1, row, color);
-        }
-        (col + c) % BUFFER_WIDTH
-    }
+    WRITER.lock().plot_num(num, col, row, color)
 }
 
 #[allow(dead_code)]
@@ -325,8 +230,7 @@ pub fn plot_num(num: isize, col: usize, row: usize, color: ColorCode) -> usize {
 ///
 /// It will **panic** given an illegal row or column.
 pub fn peek(col: usize, row: usize) -> (char, ColorCode) {
-    let result = WRITER.lock().peek(col, row);
-    (result.ascii_character as char, result.color_code)
+    Screen::peek(&*WRITER.lock(), col, row)
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,366 @@
+// VGA mode 13h (320x200, 256 colors) graphics.
+//
+// Register values and the mode switching procedure are adapted from Chris Giese's public domain
+// modes.c (https://files.osdev.org/mirrors/geezer/osd/graphics/modes.c). Drawing happens in an
+// off-screen buffer that present() copies to the linear framebuffer at 0xA0000, so a frame is
+// never shown half-drawn.
+//
+// Mode 13h overwrites the text-mode font stored in VGA plane 2, so enter_mode_13h() saves the
+// font first and enter_text_mode() restores it. The saved glyphs are also used to draw text while
+// in graphics mode.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use crate::vga_palette::{self, Rgb, PALETTE_SIZE};
+
+pub const WIDTH: usize = 320;
+pub const HEIGHT: usize = 200;
+pub const FONT_WIDTH: usize = 8;
+pub const FONT_HEIGHT: usize = 16;
+
+/// Color index that blit() leaves untouched.
+pub const TRANSPARENT: u8 = 0xFF;
+
+const FRAMEBUFFER: usize = 0xA0000;
+const GLYPH_COUNT: usize = 256;
+/// Each glyph occupies 32 bytes of plane 2, of which the text font uses the first 16.
+const GLYPH_STRIDE: usize = 32;
+
+const MISC_WRITE: u16 = 0x3C2;
+const SEQ_INDEX: u16 = 0x3C4;
+const SEQ_DATA: u16 = 0x3C5;
+const GC_INDEX: u16 = 0x3CE;
+const GC_DATA: u16 = 0x3CF;
+const CRTC_INDEX: u16 = 0x3D4;
+const CRTC_DATA: u16 = 0x3D5;
+const AC_INDEX: u16 = 0x3C0;
+const INPUT_STATUS_1: u16 = 0x3DA;
+
+struct ModeRegisters {
+    misc: u8,
+    sequencer: [u8; 5],
+    crtc: [u8; 25],
+    graphics: [u8; 9],
+    attribute: [u8; 21],
+}
+
+const MODE_13H: ModeRegisters = ModeRegisters {
+    misc: 0x63,
+    sequencer: [0x03, 0x01, 0x0F, 0x00, 0x0E],
+    crtc: [
+        0x5F, 0x4F, 0x50, 0x82, 0x54, 0x80, 0xBF, 0x1F, 0x00, 0x41, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x9C, 0x0E, 0x8F, 0x28, 0x40, 0x96, 0xB9, 0xA3, 0xFF,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x05, 0x0F, 0xFF],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E,
+        0x0F, 0x41, 0x00, 0x0F, 0x00, 0x00,
+    ],
+};
+
+const MODE_TEXT_80X25: ModeRegisters = ModeRegisters {
+    misc: 0x67,
+    sequencer: [0x03, 0x00, 0x03, 0x00, 0x02],
+    crtc: [
+        0x5F, 0x4F, 0x50, 0x82, 0x55, 0x81, 0xBF, 0x1F, 0x00, 0x4F, 0x0D, 0x0E, 0x00, 0x00, 0x00,
+        0x50, 0x9C, 0x0E, 0x8F, 0x28, 0x1F, 0x96, 0xB9, 0xA3, 0xFF,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x0E, 0x00, 0xFF],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x14, 0x07, 0x38, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E,
+        0x3F, 0x0C, 0x00, 0x0F, 0x08, 0x00,
+    ],
+};
+
+static GRAPHICS_MODE: AtomicBool = AtomicBool::new(false);
+static FONT: Mutex<[u8; GLYPH_COUNT * FONT_HEIGHT]> = Mutex::new([0; GLYPH_COUNT * FONT_HEIGHT]);
+static CANVAS: Mutex<Canvas> = Mutex::new(Canvas { pixels: [0; WIDTH * HEIGHT] });
+
+fn write_indexed(index_port: u16, data_port: u16, index: u8, value: u8) {
+    let mut index_port: Port<u8> = Port::new(index_port);
+    let mut data_port: Port<u8> = Port::new(data_port);
+    unsafe {
+        index_port.write(index);
+        data_port.write(value);
+    }
+}
+
+fn read_indexed(index_port: u16, data_port: u16, index: u8) -> u8 {
+    let mut index_port: Port<u8> = Port::new(index_port);
+    let mut data_port: Port<u8> = Port::new(data_port);
+    unsafe {
+        index_port.write(index);
+        data_port.read()
+    }
+}
+
+fn write_registers(regs: &ModeRegisters) {
+    let mut misc: Port<u8> = Port::new(MISC_WRITE);
+    let mut status: Port<u8> = Port::new(INPUT_STATUS_1);
+    let mut attribute: Port<u8> = Port::new(AC_INDEX);
+    unsafe { misc.write(regs.misc); }
+    for (i, value) in regs.sequencer.iter().enumerate() {
+        write_indexed(SEQ_INDEX, SEQ_DATA, i as u8, *value);
+    }
+    // Unlock CRTC registers 0-7, and keep them unlocked while writing the rest.
+    let crtc3 = read_indexed(CRTC_INDEX, CRTC_DATA, 0x03);
+    write_indexed(CRTC_INDEX, CRTC_DATA, 0x03, crtc3 | 0x80);
+    let crtc11 = read_indexed(CRTC_INDEX, CRTC_DATA, 0x11);
+    write_indexed(CRTC_INDEX, CRTC_DATA, 0x11, crtc11 & !0x80);
+    for (i, value) in regs.crtc.iter().enumerate() {
+        let value = match i {
+            0x03 => *value | 0x80,
+            0x11 => *value & !0x80,
+            _ => *value,
+        };
+        write_indexed(CRTC_INDEX, CRTC_DATA, i as u8, value);
+    }
+    for (i, value) in regs.graphics.iter().enumerate() {
+        write_indexed(GC_INDEX, GC_DATA, i as u8, *value);
+    }
+    for (i, value) in regs.attribute.iter().enumerate() {
+        unsafe {
+            status.read();
+            attribute.write(i as u8);
+            attribute.write(*value);
+        }
+    }
+    unsafe {
+        status.read();
+        attribute.write(0x20);
+    }
+}
+
+/// Maps plane 2 alone at 0xA0000 with flat addressing, runs **f**, then restores the registers
+/// involved. Used to reach the text-mode font.
+fn with_font_plane<F: FnOnce(*mut u8)>(f: F) {
+    let seq2 = read_indexed(SEQ_INDEX, SEQ_DATA, 2);
+    let seq4 = read_indexed(SEQ_INDEX, SEQ_DATA, 4);
+    let gc4 = read_indexed(GC_INDEX, GC_DATA, 4);
+    let gc5 = read_indexed(GC_INDEX, GC_DATA, 5);
+    let gc6 = read_indexed(GC_INDEX, GC_DATA, 6);
+
+    write_indexed(SEQ_INDEX, SEQ_DATA, 2, 0x04);
+    write_indexed(SEQ_INDEX, SEQ_DATA, 4, 0x06);
+    write_indexed(GC_INDEX, GC_DATA, 4, 0x02);
+    write_indexed(GC_INDEX, GC_DATA, 5, 0x00);
+    write_indexed(GC_INDEX, GC_DATA, 6, 0x04);
+
+    f(FRAMEBUFFER as *mut u8);
+
+    write_indexed(SEQ_INDEX, SEQ_DATA, 2, seq2);
+    write_indexed(SEQ_INDEX, SEQ_DATA, 4, seq4);
+    write_indexed(GC_INDEX, GC_DATA, 4, gc4);
+    write_indexed(GC_INDEX, GC_DATA, 5, gc5);
+    write_indexed(GC_INDEX, GC_DATA, 6, gc6);
+}
+
+fn save_font() {
+    let font = &mut *FONT.lock();
+    with_font_plane(|plane| {
+        for glyph in 0..GLYPH_COUNT {
+            for row in 0..FONT_HEIGHT {
+                font[glyph * FONT_HEIGHT + row] =
+                    unsafe { plane.add(glyph * GLYPH_STRIDE + row).read_volatile() };
+            }
+        }
+    });
+}
+
+fn restore_font() {
+    let font = &*FONT.lock();
+    with_font_plane(|plane| {
+        for glyph in 0..GLYPH_COUNT {
+            for row in 0..GLYPH_STRIDE {
+                let bits = if row < FONT_HEIGHT { font[glyph * FONT_HEIGHT + row] } else { 0 };
+                unsafe { plane.add(glyph * GLYPH_STRIDE + row).write_volatile(bits) };
+            }
+        }
+    });
+}
+
+/// Switches from 80x25 text mode to 320x200 mode 13h and loads **palette** into color indices
+/// 0 through 15, so that they match the text-mode colors.
+/// Does nothing if already in mode 13h.
+pub fn enter_mode_13h(palette: &[Rgb; PALETTE_SIZE]) {
+    if GRAPHICS_MODE.load(Ordering::SeqCst) { return; }
+    interrupts::without_interrupts(|| {
+        save_font();
+        write_registers(&MODE_13H);
+        for (i, rgb) in palette.iter().enumerate() {
+            vga_palette::set_dac(i as u8, *rgb);
+        }
+        GRAPHICS_MODE.store(true, Ordering::SeqCst);
+        clear(0);
+        present();
+    });
+}
+
+/// Switches from mode 13h back to 80x25 text mode, restoring the font.
+/// The text buffer contents are lost, so callers should clear and redraw the screen.
+/// Does nothing if already in text mode.
+pub fn enter_text_mode() {
+    if !GRAPHICS_MODE.load(Ordering::SeqCst) { return; }
+    interrupts::without_interrupts(|| {
+        write_registers(&MODE_TEXT_80X25);
+        restore_font();
+        GRAPHICS_MODE.store(false, Ordering::SeqCst);
+    });
+}
+
+/// Switches back to text mode even if a drawing lock is held, for reporting a crash.
+///
+/// # Safety
+/// Only call this when nothing else will draw again, since it ignores the locks.
+pub unsafe fn recover_text_mode() {
+    FONT.force_unlock();
+    CANVAS.force_unlock();
+    enter_text_mode();
+}
+
+/// Returns true while the display is in mode 13h.
+pub fn is_graphics_mode() -> bool {
+    GRAPHICS_MODE.load(Ordering::SeqCst)
+}
+
+/// A rectangular image whose pixels are indices into a color map given when it is drawn.
+/// Pixels that map to **TRANSPARENT** are skipped.
+pub struct Sprite<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: &'a [u8],
+}
+
+/// Off-screen drawing surface with the same dimensions as the screen.
+struct Canvas {
+    pixels: [u8; WIDTH * HEIGHT],
+}
+
+impl Canvas {
+    fn plot(&mut self, x: usize, y: usize, color: u8) {
+        if x < WIDTH && y < HEIGHT {
+            self.pixels[y * WIDTH + x] = color;
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for row in y..HEIGHT.min(y + height) {
+            for col in x..WIDTH.min(x + width) {
+                self.pixels[row * WIDTH + col] = color;
+            }
+        }
+    }
+}
+
+/// Copies the drawing canvas to the screen. Nothing drawn since the last call is visible until
+/// this is called. Does nothing outside mode 13h, so it never scribbles on the text buffer.
+pub fn present() {
+    if !is_graphics_mode() { return; }
+    let canvas = &*CANVAS.lock();
+    let vram = FRAMEBUFFER as *mut u8;
+    for (i, pixel) in canvas.pixels.iter().enumerate() {
+        unsafe { vram.add(i).write_volatile(*pixel) };
+    }
+}
+
+/// Fills the whole canvas with one color.
+pub fn clear(color: u8) {
+    CANVAS.lock().pixels.iter_mut().for_each(|p| *p = color);
+}
+
+/// Sets one pixel. Coordinates off the screen are ignored.
+pub fn plot_pixel(x: usize, y: usize, color: u8) {
+    CANVAS.lock().plot(x, y, color);
+}
+
+/// Returns the color of one pixel of the canvas.
+///
+/// It will **panic** given coordinates off the screen.
+pub fn peek_pixel(x: usize, y: usize) -> u8 {
+    CANVAS.lock().pixels[y * WIDTH + x]
+}
+
+/// Fills a rectangle, clipped to the screen.
+pub fn fill_rect(x: usize, y: usize, width: usize, height: usize, color: u8) {
+    CANVAS.lock().fill_rect(x, y, width, height, color);
+}
+
+/// Draws the outline of a rectangle **thickness** pixels wide, clipped to the screen.
+pub fn draw_rect(x: usize, y: usize, width: usize, height: usize, thickness: usize, color: u8) {
+    let canvas = &mut *CANVAS.lock();
+    let thickness = thickness.min(width / 2).min(height / 2);
+    canvas.fill_rect(x, y, width, thickness, color);
+    canvas.fill_rect(x, y + height - thickness, width, thickness, color);
+    canvas.fill_rect(x, y, thickness, height, color);
+    canvas.fill_rect(x + width - thickness, y, thickness, height, color);
+}
+
+/// Draws **sprite** with its top left corner at (x, y), clipped to the screen, so it may start
+/// partly off the screen. Each sprite pixel is looked up in **color_map**; indices past the end
+/// of the map are drawn as-is.
+pub fn blit(sprite: &Sprite, x: isize, y: isize, color_map: &[u8]) {
+    let canvas = &mut *CANVAS.lock();
+    for row in 0..sprite.height {
+        for col in 0..sprite.width {
+            let index = sprite.pixels[row * sprite.width + col];
+            let color = color_map.get(index as usize).copied().unwrap_or(index);
+            let (px, py) = (x + col as isize, y + row as isize);
+            if color != TRANSPARENT && px >= 0 && py >= 0 {
+                canvas.plot(px as usize, py as usize, color);
+            }
+        }
+    }
+}
+
+/// Draws one character using the saved text-mode font. A **background** of **TRANSPARENT**
+/// leaves the pixels behind the glyph alone.
+pub fn draw_char(c: char, x: usize, y: usize, foreground: u8, background: u8) {
+    let font = &*FONT.lock();
+    let canvas = &mut *CANVAS.lock();
+    let glyph = if c.is_ascii() { c as usize } else { 0xFE };
+    for row in 0..FONT_HEIGHT {
+        let bits = font[glyph * FONT_HEIGHT + row];
+        for col in 0..FONT_WIDTH {
+            let color = if bits & (0x80 >> col) != 0 { foreground } else { background };
+            if color != TRANSPARENT {
+                canvas.plot(x + col, y + row, color);
+            }
+        }
+    }
+}
+
+/// Draws a string starting at (x, y), with no wrapping.
+/// Returns the x coordinate just past the last character.
+pub fn draw_str(s: &str, x: usize, y: usize, foreground: u8, background: u8) -> usize {
+    let mut x = x;
+    for c in s.chars() {
+        draw_char(c, x, y, foreground, background);
+        x += FONT_WIDTH;
+    }
+    x
+}
+
+/// Draws a number starting at (x, y).
+/// Returns the x coordinate just past the last digit.
+pub fn draw_num(num: isize, x: usize, y: usize, foreground: u8, background: u8) -> usize {
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut n = num.unsigned_abs();
+    loop {
+        digits[len] = b'0' + (n % 10) as u8;
+        len += 1;
+        n /= 10;
+        if n == 0 { break; }
+    }
+    let mut x = x;
+    if num < 0 {
+        x = draw_str("-", x, y, foreground, background);
+    }
+    for i in (0..len).rev() {
+        draw_char(digits[i] as char, x, y, foreground, background);
+        x += FONT_WIDTH;
+    }
+    x
+}
@@ -0,0 +1,119 @@
+// VGA palette programming.
+//
+// In text mode, each of the 16 colors of a ColorCode selects an attribute controller palette
+// register, which in turn selects one of the 256 DAC registers that holds the actual RGB value.
+// Remapping a color therefore means finding its DAC register and rewriting it.
+//
+// Register details come from the FreeVGA project: http://www.osdever.net/FreeVGA/vga/vga.htm
+
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use crate::vga_buffer::Color;
+
+pub use match3_core::theme::{Rgb, Theme, PALETTE_SIZE};
+
+const DAC_READ_INDEX: u16 = 0x3C7;
+const DAC_WRITE_INDEX: u16 = 0x3C8;
+const DAC_DATA: u16 = 0x3C9;
+const ATTRIBUTE_ADDRESS: u16 = 0x3C0;
+const ATTRIBUTE_DATA_READ: u16 = 0x3C1;
+const INPUT_STATUS_1: u16 = 0x3DA;
+
+/// Palette Address Source bit; the display is blanked while it is clear.
+const PALETTE_ADDRESS_SOURCE: u8 = 0x20;
+
+fn to_dac(channel: u8) -> u8 {
+    channel >> 2
+}
+
+fn from_dac(channel: u8) -> u8 {
+    let channel = channel & 0x3F;
+    channel << 2 | channel >> 4
+}
+
+/// Writes an RGB value into one of the 256 DAC registers.
+pub fn set_dac(index: u8, color: Rgb) {
+    let mut write_index: Port<u8> = Port::new(DAC_WRITE_INDEX);
+    let mut data: Port<u8> = Port::new(DAC_DATA);
+    interrupts::without_interrupts(|| unsafe {
+        write_index.write(index);
+        data.write(to_dac(color.r));
+        data.write(to_dac(color.g));
+        data.write(to_dac(color.b));
+    });
+}
+
+/// Reads the RGB value stored in one of the 256 DAC registers.
+pub fn get_dac(index: u8) -> Rgb {
+    let mut read_index: Port<u8> = Port::new(DAC_READ_INDEX);
+    let mut data: Port<u8> = Port::new(DAC_DATA);
+    interrupts::without_interrupts(|| unsafe {
+        read_index.write(index);
+        let r = from_dac(data.read());
+        let g = from_dac(data.read());
+        let b = from_dac(data.read());
+        Rgb::new(r, g, b)
+    })
+}
+
+/// Points the attribute controller palette register for **color** at the given DAC register.
+pub fn set_attribute(color: Color, dac_index: u8) {
+    let mut status: Port<u8> = Port::new(INPUT_STATUS_1);
+    let mut address: Port<u8> = Port::new(ATTRIBUTE_ADDRESS);
+    interrupts::without_interrupts(|| unsafe {
+        // Reading the status register resets the address/data flip-flop.
+        status.read();
+        address.write(color as u8);
+        address.write(dac_index);
+        status.read();
+        address.write(PALETTE_ADDRESS_SOURCE);
+    });
+}
+
+/// Returns the DAC register selected by the attribute controller palette register for **color**.
+pub fn get_attribute(color: Color) -> u8 {
+    let mut status: Port<u8> = Port::new(INPUT_STATUS_1);
+    let mut address: Port<u8> = Port::new(ATTRIBUTE_ADDRESS);
+    let mut data: Port<u8> = Port::new(ATTRIBUTE_DATA_READ);
+    interrupts::without_interrupts(|| unsafe {
+        status.read();
+        address.write(color as u8);
+        let dac_index = data.read();
+        status.read();
+        address.write(PALETTE_ADDRESS_SOURCE);
+        dac_index
+    })
+}
+
+/// Changes the RGB value displayed for **color**.
+pub fn set_color(color: Color, rgb: Rgb) {
+    set_dac(get_attribute(color), rgb);
+}
+
+/// Returns the RGB value currently displayed for **color**.
+pub fn get_color(color: Color) -> Rgb {
+    get_dac(get_attribute(color))
+}
+
+/// Writes all 16 colors at once, indexed by their Color value.
+pub fn set_palette(palette: &[Rgb; PALETTE_SIZE]) {
+    for (i, rgb) in palette.iter().enumerate() {
+        set_color(Color::from(i as u8), *rgb);
+    }
+}
+
+/// Rotates colors through a set of palette slots. Slot **slots[i]** receives the **palette**
+/// color for **sources[(i + step) % sources.len()]**. Since only the DAC changes, anything drawn
+/// in those slots animates without being redrawn.
+pub fn cycle(palette: &[Rgb; PALETTE_SIZE], slots: &[Color], sources: &[Color], step: usize) {
+    if sources.is_empty() { return; }
+    for (i, slot) in slots.iter().enumerate() {
+        let source = sources[(i + step) % sources.len()];
+        set_color(*slot, palette[source as usize]);
+    }
+}
+
+/// Loads **theme** into the VGA palette.
+pub fn apply_theme(theme: Theme) {
+    set_palette(&theme.palette());
+}
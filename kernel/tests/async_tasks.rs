@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use csci320_match3::task::{timer, Executor, Task};
+use csci320_match3::{entry_point, BootInfo, HandlerTable};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    HandlerTable::new().boot_info(boot_info).install();
+    test_main();
+    loop {}
+}
+
+async fn number() -> u32 {
+    42
+}
+
+#[test_case]
+fn tasks_run_to_completion() {
+    let result = Rc::new(RefCell::new(0));
+    let mut executor = Executor::new();
+    let out = result.clone();
+    executor.spawn(Task::new(async move {
+        *out.borrow_mut() = number().await;
+    }));
+    executor.run_until_complete();
+    assert_eq!(*result.borrow(), 42);
+}
+
+#[test_case]
+fn sleep_waits_for_ticks() {
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(async {
+        let start = timer::ticks();
+        timer::sleep(3).await;
+        assert!(timer::ticks() >= start + 3);
+    }));
+    executor.run_until_complete();
+}
+
+#[test_case]
+fn shorter_sleeps_finish_first() {
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let mut executor = Executor::new();
+    for (id, ticks) in [(0, 4), (1, 1), (2, 2)] {
+        let order = order.clone();
+        executor.spawn(Task::new(async move {
+            timer::sleep(ticks).await;
+            order.borrow_mut().push(id);
+        }));
+    }
+    executor.run_until_complete();
+    assert_eq!(*order.borrow(), [1, 2, 0]);
+}
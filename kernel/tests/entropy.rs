@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use csci320_match3::entropy::{self, Source};
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    loop {}
+}
+
+#[test_case]
+fn qemu_cpu_max_has_rdrand() {
+    // the tests run with -cpu max
+    assert!(entropy::has_rdrand());
+    assert_ne!(entropy::source(), Source::Jitter);
+}
+
+#[test_case]
+fn random_values_differ() {
+    let values = [entropy::random_u64(), entropy::random_u64(), entropy::random_u64()];
+    assert_ne!(values[0], values[1]);
+    assert_ne!(values[1], values[2]);
+}
+
+#[test_case]
+fn jitter_values_differ() {
+    let values = [entropy::jitter_u64(), entropy::jitter_u64(), entropy::jitter_u64()];
+    assert_ne!(values[0], values[1]);
+    assert_ne!(values[1], values[2]);
+}
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use csci320_match3::events::{Event, EventQueue};
+use pc_keyboard::DecodedKey;
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    loop {}
+}
+
+#[test_case]
+fn events_come_out_in_order() {
+    let queue: EventQueue<4> = EventQueue::new();
+    unsafe {
+        assert!(queue.push(Event::Tick));
+        assert!(queue.push(Event::Key(DecodedKey::Unicode('a'))));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop(), Some(Event::Tick));
+        assert_eq!(queue.pop(), Some(Event::Key(DecodedKey::Unicode('a'))));
+        assert_eq!(queue.pop(), None);
+    }
+    assert!(queue.is_empty());
+}
+
+#[test_case]
+fn full_queue_counts_overflows() {
+    let queue: EventQueue<4> = EventQueue::new();
+    unsafe {
+        for c in ['a', 'b', 'c', 'd'] {
+            assert!(queue.push(Event::Key(DecodedKey::Unicode(c))));
+        }
+        assert!(!queue.push(Event::Tick));
+        assert!(!queue.push(Event::Tick));
+        assert_eq!(queue.overflow_count(), 2);
+        assert_eq!(queue.pop(), Some(Event::Key(DecodedKey::Unicode('a'))));
+        assert!(queue.push(Event::Tick));
+    }
+    assert_eq!(queue.len(), 4);
+    assert_eq!(queue.overflow_count(), 2);
+}
+
+#[test_case]
+fn indices_wrap_around() {
+    let queue: EventQueue<2> = EventQueue::new();
+    for i in 0..10u8 {
+        let key = Event::Key(DecodedKey::Unicode((b'0' + i) as char));
+        unsafe {
+            assert!(queue.push(key));
+            assert_eq!(queue.pop(), Some(key));
+        }
+    }
+    assert_eq!(queue.overflow_count(), 0);
+}
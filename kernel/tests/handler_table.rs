@@ -0,0 +1,65 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use csci320_match3::HandlerTable;
+use pc_keyboard::{DecodedKey, KeyCode};
+use spin::Mutex;
+
+static TICKS: AtomicUsize = AtomicUsize::new(0);
+static INTERRUPT_TICKS: AtomicUsize = AtomicUsize::new(0);
+static LAST_KEY: Mutex<Option<DecodedKey>> = Mutex::new(None);
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    loop {}
+}
+
+fn count_tick() {
+    TICKS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn count_interrupt_tick() {
+    INTERRUPT_TICKS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn remember_key(key: DecodedKey) {
+    *LAST_KEY.lock() = Some(key);
+}
+
+#[test_case]
+fn timer_dispatch() {
+    let table = HandlerTable::new().timer(count_tick);
+    let before = TICKS.load(Ordering::SeqCst);
+    table.handle_timer();
+    table.handle_timer();
+    assert_eq!(TICKS.load(Ordering::SeqCst), before + 2);
+}
+
+#[test_case]
+fn keyboard_dispatch() {
+    let table = HandlerTable::new().keyboard(remember_key);
+    table.handle_keyboard(DecodedKey::Unicode('m'));
+    assert_eq!(*LAST_KEY.lock(), Some(DecodedKey::Unicode('m')));
+    table.handle_keyboard(DecodedKey::RawKey(KeyCode::ArrowUp));
+    assert_eq!(*LAST_KEY.lock(), Some(DecodedKey::RawKey(KeyCode::ArrowUp)));
+}
+
+#[test_case]
+fn missing_handlers_are_ignored() {
+    let table = HandlerTable::new();
+    table.handle_timer();
+    table.handle_keyboard(DecodedKey::Unicode('q'));
+}
+
+#[test_case]
+fn timer_interrupts_reach_handler() {
+    HandlerTable::new().timer(count_interrupt_tick).install();
+    while INTERRUPT_TICKS.load(Ordering::SeqCst) < 3 {
+        x86_64::instructions::hlt();
+    }
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+// Adapted from https://os.phil-opp.com/heap-allocation/#adding-a-test
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use csci320_match3::allocator::HEAP_SIZE;
+use csci320_match3::{entry_point, BootInfo, HandlerTable};
+
+entry_point!(main);
+
+fn main(boot_info: &'static BootInfo) -> ! {
+    HandlerTable::new().boot_info(boot_info).install();
+    test_main();
+    loop {}
+}
+
+#[test_case]
+fn simple_allocation() {
+    let heap_value_1 = Box::new(41);
+    let heap_value_2 = Box::new(13);
+    assert_eq!(*heap_value_1, 41);
+    assert_eq!(*heap_value_2, 13);
+}
+
+#[test_case]
+fn large_vec() {
+    let n = 1000;
+    let mut vec = Vec::new();
+    for i in 0..n {
+        vec.push(i);
+    }
+    assert_eq!(vec.iter().sum::<u64>(), (n - 1) * n / 2);
+}
+
+#[test_case]
+fn many_boxes() {
+    for i in 0..HEAP_SIZE {
+        let x = Box::new(i);
+        assert_eq!(*x, i);
+    }
+}
+
+#[test_case]
+fn string_and_btree_map() {
+    let mut scores = BTreeMap::new();
+    let mut name = String::from("match");
+    name.push('3');
+    scores.insert(name, 300);
+    scores.insert(String::from("bejeweled"), 100);
+    assert_eq!(scores.keys().next().map(|k| k.as_str()), Some("bejeweled"));
+    assert_eq!(scores.get("match3"), Some(&300));
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use csci320_match3::{rtc, HandlerTable};
+
+static RTC_TICKS: AtomicUsize = AtomicUsize::new(0);
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    loop {}
+}
+
+fn count_rtc_tick() {
+    RTC_TICKS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test_case]
+fn date_and_time_are_in_range() {
+    let now = rtc::read();
+    assert!(now.date.year >= 2000);
+    assert!((1..=12).contains(&now.date.month));
+    assert!((1..=31).contains(&now.date.day));
+    assert!(now.hour < 24);
+    assert!(now.minute < 60);
+    assert!(now.second < 60);
+}
+
+#[test_case]
+fn clock_does_not_go_backwards() {
+    let first = rtc::read();
+    let second = rtc::read();
+    assert!(second >= first);
+}
+
+#[test_case]
+fn periodic_interrupts_reach_handler() {
+    HandlerTable::new().rtc(count_rtc_tick).install();
+    rtc::enable_periodic_interrupt(6);
+    while RTC_TICKS.load(Ordering::SeqCst) < 3 {
+        x86_64::instructions::hlt();
+    }
+    rtc::disable_periodic_interrupt();
+}
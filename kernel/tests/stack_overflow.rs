@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+#![feature(abi_x86_interrupt)]
+
+// Adapted from https://os.phil-opp.com/double-fault-exceptions/#a-stack-overflow-test
+//
+// Overflowing the kernel stack causes a page fault on the guard page, and pushing the page fault's
+// stack frame faults again, so the CPU raises a double fault. That only reaches a handler if the
+// handler runs on its own stack, which gdt::init() provides through the TSS. Without it, QEMU
+// would triple fault and reboot.
+
+use csci320_match3::testing::{enter_test_mode, exit_qemu, QemuExitCode};
+use csci320_match3::{gdt, serial_print, serial_println};
+use lazy_static::lazy_static;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+
+lazy_static! {
+    static ref TEST_IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(test_double_fault_handler)
+                .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    serial_print!("stack_overflow::stack_overflow...\t");
+    enter_test_mode();
+
+    gdt::init();
+    TEST_IDT.load();
+
+    stack_overflow();
+
+    panic!("Execution continued after stack overflow");
+}
+
+#[allow(unconditional_recursion)]
+fn stack_overflow() {
+    stack_overflow();
+    volatile::Volatile::new(0).read(); // prevent tail recursion optimizations
+}
+
+extern "x86-interrupt" fn test_double_fault_handler(
+    _stack_frame: InterruptStackFrame, _error_code: u64) -> !
+{
+    serial_println!("[ok]");
+    exit_qemu(QemuExitCode::Success);
+    loop {}
+}
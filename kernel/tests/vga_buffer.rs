@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(csci320_match3::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use csci320_match3::vga_buffer::{
+    self, clear_row, num_str_len, peek, plot, plot_num, plot_num_right_justified, plot_str, Color,
+    ColorCode, BUFFER_HEIGHT, BUFFER_WIDTH,
+};
+
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+    loop {}
+}
+
+fn row_text(row: usize, col: usize, len: usize) -> [char; BUFFER_WIDTH] {
+    let mut text = [' '; BUFFER_WIDTH];
+    for i in 0..len {
+        text[i] = peek(col + i, row).0;
+    }
+    text
+}
+
+#[test_case]
+fn plot_then_peek() {
+    let color = ColorCode::new(Color::Yellow, Color::Blue);
+    plot('x', 3, 4, color);
+    assert_eq!(peek(3, 4), ('x', color));
+}
+
+#[test_case]
+fn plot_corners() {
+    let color = ColorCode::new(Color::White, Color::Black);
+    plot('a', 0, 0, color);
+    plot('b', BUFFER_WIDTH - 1, BUFFER_HEIGHT - 1, color);
+    assert_eq!(peek(0, 0).0, 'a');
+    assert_eq!(peek(BUFFER_WIDTH - 1, BUFFER_HEIGHT - 1).0, 'b');
+}
+
+#[test_case]
+fn plot_str_returns_next_column() {
+    let color = ColorCode::new(Color::LightGreen, Color::Black);
+    let next = plot_str("Match3", 10, 2, color);
+    assert_eq!(next, 16);
+    assert_eq!(&row_text(2, 10, 6)[..6], &['M', 'a', 't', 'c', 'h', '3']);
+    assert_eq!(peek(12, 2).1, color);
+}
+
+#[test_case]
+fn plot_str_truncates_at_right_edge() {
+    let color = ColorCode::new(Color::White, Color::Black);
+    let next = plot_str("abcdef", BUFFER_WIDTH - 3, 5, color);
+    assert_eq!(next, 0);
+    assert_eq!(&row_text(5, BUFFER_WIDTH - 3, 3)[..3], &['a', 'b', 'c']);
+}
+
+#[test_case]
+fn plot_numbers() {
+    let color = ColorCode::new(Color::White, Color::Black);
+    assert_eq!(num_str_len(0), 1);
+    assert_eq!(num_str_len(-45), 3);
+    assert_eq!(num_str_len(12345), 5);
+    assert_eq!(plot_num(-45, 0, 6, color), 3);
+    assert_eq!(&row_text(6, 0, 3)[..3], &['-', '4', '5']);
+    assert_eq!(plot_num_right_justified(6, 300, 0, 7, color), 6);
+    assert_eq!(&row_text(7, 0, 6)[..6], &[' ', ' ', ' ', '3', '0', '0']);
+}
+
+#[test_case]
+fn clear_row_sets_background() {
+    plot('z', 8, 9, ColorCode::new(Color::Red, Color::Black));
+    clear_row(9, Color::Cyan);
+    assert_eq!(peek(8, 9), (' ', ColorCode::new(Color::Cyan, Color::Cyan)));
+}
+
+#[test_case]
+fn println_writes_bottom_row() {
+    let s = "Some test string that fits on a single line";
+    csci320_match3::println!("{}", s);
+    for (i, c) in s.chars().enumerate() {
+        assert_eq!(vga_buffer::peek(i, BUFFER_HEIGHT - 2).0, c);
+    }
+}
@@ -0,0 +1,19 @@
+// Plays the game with fuzzer-chosen seeds and inputs, checking the engine's invariants after
+// every step. See match3game/invariants.rs for what is checked.
+//
+// The first 8 bytes of the input are the seed and the next byte picks the gem policy. Every
+// byte after that is one step: a key, a legal move, or a wait.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use match3_core::gem_generator::GemPolicy;
+use match3_core::match3game::invariants::{self, Step};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((seed, rest)) = data.split_first_chunk::<8>() else { return };
+    let Some((policy, steps)) = rest.split_first() else { return };
+    let policy = GemPolicy::ALL[*policy as usize % GemPolicy::ALL.len()];
+    let steps: Vec<Step> = steps.iter().map(|byte| Step::from_byte(*byte)).collect();
+    invariants::check(u64::from_le_bytes(*seed), policy, &steps);
+});
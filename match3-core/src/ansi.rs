@@ -0,0 +1,258 @@
+// Shows a Screen on an ANSI terminal, such as match3-term's or one at the other end of the
+// kernel's serial port.
+//
+// Cells are placed with cursor-position escape codes and colored with SGR codes. AnsiTerminal
+// remembers what the terminal shows, so each frame only sends the cells that changed; over a
+// serial line at 38400 baud, sending all 2000 cells every tick would not keep up.
+//
+// AnsiMirror is the Screen for a terminal: the screens in draw.rs draw on it as they do on the
+// VGA Writer, and flush() sends the terminal the cells that changed.
+
+use core::fmt::{self, Write};
+use crate::screen::{Color, ColorCode, Screen, TextBuffer};
+use crate::theme::{Rgb, PALETTE_SIZE};
+
+/// Puts the terminal back to its default colors.
+pub const RESET: &str = "\x1b[0m";
+/// Clears the terminal and hides its cursor, before the whole screen is sent.
+const CLEAR: &str = "\x1b[0m\x1b[2J\x1b[?25l";
+
+/// Where the kernel draws its frames: on the VGA screen, on the serial console, or on both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialOutput { Mirror, Only, Off }
+
+impl SerialOutput {
+    pub fn name(self) -> &'static str {
+        match self {
+            SerialOutput::Mirror => "VGA + serial",
+            SerialOutput::Only => "Serial only",
+            SerialOutput::Off => "VGA only",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SerialOutput::Mirror => SerialOutput::Only,
+            SerialOutput::Only => SerialOutput::Off,
+            SerialOutput::Off => SerialOutput::Mirror,
+        }
+    }
+
+    /// Whether frames are drawn on the VGA screen.
+    pub fn vga(self) -> bool {
+        self != SerialOutput::Only
+    }
+
+    /// Whether frames are sent to the serial console.
+    pub fn serial(self) -> bool {
+        self != SerialOutput::Off
+    }
+}
+
+/// How a terminal is told the 16 VGA colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colors {
+    /// The terminal's own 16 colors, which take a few bytes per change.
+    Basic,
+    /// 24-bit colors from a theme's palette, for terminals that support them.
+    Rgb([Rgb; PALETTE_SIZE]),
+}
+
+/// Writes the escape code that moves the terminal's cursor to (**col**, **row**), counting from 0.
+pub fn move_to(out: &mut impl Write, col: usize, row: usize) -> fmt::Result {
+    write!(out, "\x1b[{};{}H", row + 1, col + 1)
+}
+
+/// Writes the escape code that draws in **color**, as **colors** shows it.
+pub fn set_color(out: &mut impl Write, color: ColorCode, colors: &Colors) -> fmt::Result {
+    match colors {
+        Colors::Basic => {
+            let (fg, fg_bright) = basic_color(color.foreground());
+            let (bg, bg_bright) = basic_color(color.background());
+            let fg = if fg_bright { 90 } else { 30 } + fg;
+            let bg = if bg_bright { 100 } else { 40 } + bg;
+            write!(out, "\x1b[{};{}m", fg, bg)
+        },
+        Colors::Rgb(palette) => {
+            let fg = palette[color.foreground() as usize];
+            let bg = palette[color.background() as usize];
+            write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m", fg.r, fg.g, fg.b, bg.r, bg.g, bg.b)
+        },
+    }
+}
+
+/// Returns the ANSI color number of **color**, and whether it is the bright version. VGA numbers
+/// its colors with blue in the lowest bit, and ANSI with red.
+fn basic_color(color: Color) -> (u8, bool) {
+    const ANSI_ORDER: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+    let n = color as u8;
+    (ANSI_ORDER[(n & 7) as usize], n >= 8)
+}
+
+/// What a terminal is showing, so that only the cells that change need to be sent to it.
+pub struct AnsiTerminal {
+    shown: TextBuffer,
+    /// Whether the terminal's contents are unknown, so the next update sends every cell.
+    stale: bool,
+    colors: Colors,
+}
+
+impl AnsiTerminal {
+    /// A terminal that has not been drawn on yet.
+    pub fn new(colors: Colors) -> Self {
+        AnsiTerminal { shown: TextBuffer::new(), stale: true, colors }
+    }
+
+    /// Makes the next update send the whole screen, for a terminal that has just connected or
+    /// whose contents were lost.
+    pub fn forget(&mut self) {
+        self.stale = true;
+    }
+
+    /// Changes how colors are sent. Every cell is sent again if they changed.
+    pub fn set_colors(&mut self, colors: Colors) {
+        if colors != self.colors {
+            self.colors = colors;
+            self.forget();
+        }
+    }
+
+    /// Writes to **out** the escape codes that turn the terminal into a copy of **screen**.
+    pub fn update(&mut self, screen: &impl Screen, out: &mut impl Write) -> fmt::Result {
+        let (width, height) = self.shown.size();
+        let everything = self.stale;
+        if everything {
+            out.write_str(CLEAR)?;
+            self.stale = false;
+        }
+        let mut color = None;
+        // where the terminal's cursor is, when it is known
+        let mut cursor = None;
+        for row in 0..height {
+            for col in 0..width {
+                let (c, cell_color) = screen.peek(col, row);
+                if !everything && self.shown.peek(col, row) == (c, cell_color) {
+                    continue;
+                }
+                self.shown.plot(c, col, row, cell_color);
+                if cursor != Some((col, row)) {
+                    move_to(out, col, row)?;
+                }
+                if color != Some(cell_color) {
+                    set_color(out, cell_color, &self.colors)?;
+                    color = Some(cell_color);
+                }
+                out.write_char(c)?;
+                // terminals differ on where the cursor goes after writing in the last column
+                cursor = if col + 1 < width { Some((col + 1, row)) } else { None };
+            }
+        }
+        if color.is_some() {
+            out.write_str(RESET)?;
+        }
+        Ok(())
+    }
+}
+
+/// A Screen shown on an ANSI terminal. Drawing changes a frame in memory, and flush() sends the
+/// terminal whatever changed since the last flush.
+pub struct AnsiMirror {
+    frame: TextBuffer,
+    terminal: AnsiTerminal,
+}
+
+impl AnsiMirror {
+    /// A blank screen for a terminal that has not been drawn on yet.
+    pub fn new(colors: Colors) -> Self {
+        AnsiMirror { frame: TextBuffer::new(), terminal: AnsiTerminal::new(colors) }
+    }
+
+    /// Makes the next flush send the whole screen. See AnsiTerminal::forget().
+    pub fn forget(&mut self) {
+        self.terminal.forget();
+    }
+
+    /// Changes how colors are sent. See AnsiTerminal::set_colors().
+    pub fn set_colors(&mut self, colors: Colors) {
+        self.terminal.set_colors(colors);
+    }
+
+    /// Writes to **out** the escape codes that bring the terminal up to date with this screen.
+    pub fn flush(&mut self, out: &mut impl Write) -> fmt::Result {
+        self.terminal.update(&self.frame, out)
+    }
+}
+
+impl Screen for AnsiMirror {
+    fn size(&self) -> (usize, usize) {
+        self.frame.size()
+    }
+
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        self.frame.plot(c, col, row, color);
+    }
+
+    fn peek(&self, col: usize, row: usize) -> (char, ColorCode) {
+        self.frame.peek(col, row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    const CODE: ColorCode = ColorCode::new(Color::Yellow, Color::Blue);
+
+    #[test]
+    fn only_changed_cells_are_sent() {
+        let mut terminal = AnsiTerminal::new(Colors::Basic);
+        let mut screen = TextBuffer::new();
+        let mut out = String::new();
+        terminal.update(&screen, &mut out).unwrap();
+        assert!(out.starts_with(CLEAR));
+        screen.plot_str("Hi", 3, 1, CODE);
+        screen.plot('!', 10, 1, CODE);
+        let mut out = String::new();
+        terminal.update(&screen, &mut out).unwrap();
+        assert_eq!(out, "\x1b[2;4H\x1b[93;44mHi\x1b[2;11H!\x1b[0m");
+        let mut out = String::new();
+        terminal.update(&screen, &mut out).unwrap();
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn forgetting_sends_everything_again() {
+        let mut terminal = AnsiTerminal::new(Colors::Basic);
+        let screen = TextBuffer::new();
+        terminal.update(&screen, &mut String::new()).unwrap();
+        terminal.set_colors(Colors::Rgb(crate::theme::Theme::Classic.palette()));
+        let mut out = String::new();
+        terminal.update(&screen, &mut out).unwrap();
+        assert_eq!(out.matches(' ').count(), 80 * 25);
+    }
+
+    #[test]
+    fn mirrors_send_what_is_drawn_on_them() {
+        let mut mirror = AnsiMirror::new(Colors::Basic);
+        mirror.flush(&mut String::new()).unwrap();
+        mirror.plot_str("Hi", 3, 1, CODE);
+        assert_eq!(mirror.peek(4, 1), ('i', CODE));
+        let mut out = String::new();
+        mirror.flush(&mut out).unwrap();
+        assert_eq!(out, "\x1b[2;4H\x1b[93;44mHi\x1b[0m");
+        // redrawing the same frame sends nothing
+        mirror.clear_screen();
+        mirror.plot_str("Hi", 3, 1, CODE);
+        let mut out = String::new();
+        mirror.flush(&mut out).unwrap();
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn basic_colors_follow_ansi_order() {
+        let mut out = String::new();
+        set_color(&mut out, ColorCode::new(Color::Red, Color::LightCyan), &Colors::Basic).unwrap();
+        assert_eq!(out, "\x1b[31;106m");
+    }
+}
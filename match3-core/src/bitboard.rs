@@ -0,0 +1,209 @@
+// Bitboards for finding matches and legal moves without looping over the board.
+//
+// Each gem color gets one u64 with a bit per cell. Bit col * BOARD_HEIGHT + row stands for
+// board[col][row], so each column is a run of BOARD_HEIGHT bits. Moving every cell of a bitboard
+// one step in some direction is then a single shift, and masking keeps gems from wrapping
+// around from the bottom of one column to the top of the next.
+
+use crate::match3game::{BOARD_HEIGHT, BOARD_WIDTH, GEM_COUNT};
+
+const _: () = assert!(BOARD_WIDTH * BOARD_HEIGHT <= 64, "the board must fit in a u64");
+
+/// A set of cells on the board.
+pub type Bitboard = u64;
+
+/// Every cell on the board.
+const ALL_CELLS: Bitboard = if BOARD_WIDTH * BOARD_HEIGHT == 64 { !0 } else { (1 << (BOARD_WIDTH * BOARD_HEIGHT)) - 1 };
+/// The cells in the top row.
+const TOP_ROW: Bitboard = row_mask(0);
+/// The cells in the bottom row.
+const BOTTOM_ROW: Bitboard = row_mask(BOARD_HEIGHT - 1);
+
+const fn row_mask(row: usize) -> Bitboard {
+    let mut mask = 0;
+    let mut col = 0;
+    while col < BOARD_WIDTH {
+        mask |= cell(col, row);
+        col += 1;
+    }
+    mask
+}
+
+/// The bitboard holding just (**col**, **row**).
+pub const fn cell(col: usize, row: usize) -> Bitboard {
+    1 << (col * BOARD_HEIGHT + row)
+}
+
+/// The (col, row) of the lowest cell in **cells**, which must not be empty.
+fn location(cells: Bitboard) -> (usize, usize) {
+    let index = cells.trailing_zeros() as usize;
+    (index / BOARD_HEIGHT, index % BOARD_HEIGHT)
+}
+
+/// Moves every cell one row up. Cells in the top row fall off the board.
+fn up(cells: Bitboard) -> Bitboard {
+    (cells & !TOP_ROW) >> 1
+}
+
+/// Moves every cell one row down. Cells in the bottom row fall off the board.
+fn down(cells: Bitboard) -> Bitboard {
+    (cells & !BOTTOM_ROW) << 1
+}
+
+/// Moves every cell one column left. Cells in the leftmost column fall off the board.
+fn left(cells: Bitboard) -> Bitboard {
+    cells >> BOARD_HEIGHT
+}
+
+/// Moves every cell one column right. Cells in the rightmost column fall off the board.
+fn right(cells: Bitboard) -> Bitboard {
+    (cells << BOARD_HEIGHT) & ALL_CELLS
+}
+
+/// Every cell next to one of **cells**, up, down, left or right.
+pub fn neighbors(cells: Bitboard) -> Bitboard {
+    up(cells) | down(cells) | left(cells) | right(cells)
+}
+
+/// The direction that a line of matching gems runs in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Line { Vertical, Horizontal }
+
+impl Line {
+    /// Moves every cell one step along the line, toward its end.
+    fn forward(self, cells: Bitboard) -> Bitboard {
+        match self {
+            Line::Vertical => down(cells),
+            Line::Horizontal => right(cells),
+        }
+    }
+
+    /// Moves every cell one step along the line, toward its start.
+    fn backward(self, cells: Bitboard) -> Bitboard {
+        match self {
+            Line::Vertical => up(cells),
+            Line::Horizontal => left(cells),
+        }
+    }
+
+    /// The cells of **gems** that are in a line of three or more along this direction.
+    fn matches(self, gems: Bitboard) -> Bitboard {
+        // cells that start a line of three
+        let starts = gems & self.backward(gems) & self.backward(self.backward(gems));
+        starts | self.forward(starts) | self.forward(self.forward(starts))
+    }
+
+    /// True if one of **gems** can swap with one of **others** and complete a line of three in
+    /// this direction. The gem cannot come from either of the two cells it lines up with, since
+    /// leaving one of them breaks the line.
+    fn has_move(self, gems: Bitboard, others: Bitboard) -> bool {
+        let (ahead, behind) = (self.backward(gems), self.forward(gems));
+        let across = match self {
+            Line::Vertical => left(gems) | right(gems),
+            Line::Horizontal => up(gems) | down(gems),
+        };
+        // the two gems are ahead of the cell, behind it, or on both sides
+        let before_pair = ahead & self.backward(ahead) & others;
+        let after_pair = behind & self.forward(behind) & others;
+        let between = ahead & behind & others;
+        before_pair & (across | behind) != 0
+            || after_pair & (across | ahead) != 0
+            || between & across != 0
+    }
+}
+
+/// One line of three or more matching gems.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Run {
+    pub col: usize,
+    pub row: usize,
+    pub len: usize,
+}
+
+/// The board as one bitboard per color.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Bitboards {
+    colors: [Bitboard; GEM_COUNT],
+}
+
+impl Bitboards {
+    /// Empty cells, marked 0, are in none of the colors.
+    pub fn from_board(board: &[[u8; BOARD_HEIGHT]; BOARD_WIDTH]) -> Self {
+        let mut boards = Bitboards { colors: [0; GEM_COUNT] };
+        for (col, column) in board.iter().enumerate() {
+            for (row, gem) in column.iter().enumerate() {
+                boards.place(col, row, *gem);
+            }
+        }
+        boards
+    }
+
+    /// Puts **gem** at (**col**, **row**), replacing whatever was there. A **gem** of 0 empties
+    /// the cell.
+    pub fn place(&mut self, col: usize, row: usize, gem: u8) {
+        let bit = cell(col, row);
+        for color in self.colors.iter_mut() {
+            *color &= !bit;
+        }
+        if (1..=GEM_COUNT as u8).contains(&gem) {
+            self.colors[gem as usize - 1] |= bit;
+        }
+    }
+
+    /// Every cell that is part of a line of three or more matching gems.
+    pub fn matches(&self) -> Bitboard {
+        self.colors.iter()
+            .map(|gems| Line::Vertical.matches(*gems) | Line::Horizontal.matches(*gems))
+            .fold(0, |all, gems| all | gems)
+    }
+
+    /// True if swapping some pair of neighboring gems lines up three or more of a color that
+    /// were not lined up before. Empty cells never match or swap.
+    pub fn has_legal_move(&self) -> bool {
+        let occupied = self.colors.iter().fold(0, |all, gems| all | gems);
+        self.colors.iter().any(|gems| {
+            let others = occupied & !gems;
+            Line::Vertical.has_move(*gems, others) || Line::Horizontal.has_move(*gems, others)
+        })
+    }
+
+    /// Every line of three or more along **line**, ordered by the column and then the row of
+    /// its first cell.
+    pub fn runs(&self, line: Line) -> Runs {
+        let mut matched = [0; GEM_COUNT];
+        let mut starts = 0;
+        for (lines, gems) in matched.iter_mut().zip(self.colors.iter()) {
+            *lines = line.matches(*gems);
+            starts |= *lines & !line.forward(*lines);
+        }
+        Runs { line, matched, starts }
+    }
+}
+
+/// Iterator over the lines found by Bitboards::runs().
+pub struct Runs {
+    line: Line,
+    matched: [Bitboard; GEM_COUNT],
+    starts: Bitboard,
+}
+
+impl Iterator for Runs {
+    type Item = Run;
+
+    fn next(&mut self) -> Option<Run> {
+        if self.starts == 0 {
+            return None;
+        }
+        let start = self.starts & self.starts.wrapping_neg();
+        self.starts &= !start;
+        let matched = self.matched.iter().find(|m| *m & start != 0).copied().unwrap_or(0);
+        let mut len = 0;
+        let mut next = start;
+        while next & matched != 0 {
+            len += 1;
+            next = self.line.forward(next);
+        }
+        let (col, row) = location(start);
+        Some(Run { col, row, len })
+    }
+}
@@ -0,0 +1,356 @@
+// The text-mode screens: the logo and code menu, the wait for a versus opponent, the pause and
+// settings menus, and the board.
+//
+// Each one draws on any Screen, so the kernel draws them on the VGA buffer, match3-term on a
+// TextBuffer it sends to the terminal, and the snapshot tests on a TextBuffer they compare with
+// the files in tests/snapshots. The screens are drawn over the previous frame rather than on a
+// cleared screen, so each one clears the cells it might have left behind.
+
+use alloc::format;
+use crate::link::LinkState;
+use crate::match3game::{self, GameState, GameStateManager, Outcome, PauseOption, SettingsOption};
+use crate::screen::{num_str_len, Color, ColorCode, Screen, BUFFER_HEIGHT, BUFFER_WIDTH};
+
+/// The bright gem colors, which the logo's letters cycle through.
+pub const LOGO_COLORS: [Color; 7] = [
+    Color::LightBlue, Color::LightGreen, Color::LightCyan, Color::LightRed, Color::Pink, Color::Yellow, Color::White
+];
+
+/// Ticks between steps of the logo's color cycle.
+pub const FLASH_PERIOD: u64 = 10;
+
+/// Draws whichever screen the game is on, with the logo's letters cycling through LOGO_COLORS.
+/// The board is always drawn in text, whatever the display setting.
+pub fn draw_screen(screen: &mut impl Screen, gsm: &GameStateManager, tick: u64) {
+    match gsm.get_state() {
+        GameState::EnteringCode => {
+            let step = (tick / FLASH_PERIOD) as usize;
+            let colors = core::array::from_fn(|letter| LOGO_COLORS[(letter + step) % LOGO_COLORS.len()]);
+            draw_logo(screen, &colors);
+            draw_code_menu(screen, gsm);
+        },
+        GameState::Connecting => draw_connecting(screen, gsm),
+        GameState::Playing => draw_game(screen, gsm, tick),
+        GameState::Paused => draw_pause_menu(screen, gsm),
+        GameState::Settings => draw_settings_menu(screen, gsm),
+    }
+}
+
+/// Draws the Match3 logo with letter i in **colors**[i % 7].
+pub fn draw_logo(screen: &mut impl Screen, colors: &[Color; 7]) {
+    const LOGO_HEIGHT: usize = 4;
+    const LOGO_LENGTH: usize = 41;
+    const LOGO_DRAW_ROW: usize = 5;
+    const LOGO_DRAW_COL: usize = (BUFFER_WIDTH - LOGO_LENGTH) / 2;
+    const LETTER_OFFSETS: [usize; 8] = [0, 8, 14, 19, 23, 31, 38, LOGO_LENGTH];
+    // ASCII art generated from https://texteditor.com/ascii-art/ using the "Meh" font
+    const LETTERS: &str = r" __  __        _        _       ____   _ |  \/  | __ _ | |_  __ | |_    |__ /  | || |\/| |/ _` ||  _|/ _||   \    |_ \  |_||_|  |_|\__/_| \__|\__||_||_|  |___/  (_)";
+    for letter in 0..LETTER_OFFSETS.len()-1 {
+        let chosen_color = colors[letter % colors.len()];
+        for row in 0..LOGO_HEIGHT {
+            let letter_start = LOGO_LENGTH * row + LETTER_OFFSETS[letter];
+            let letter_end = LOGO_LENGTH * row + LETTER_OFFSETS[letter + 1];
+            screen.plot_str(
+                &LETTERS[letter_start..letter_end],
+                LOGO_DRAW_COL + LETTER_OFFSETS[letter],
+                LOGO_DRAW_ROW + row,
+                ColorCode::new(chosen_color, Color::Black)
+            );
+        }
+    }
+}
+
+/// Draws the code being typed, the hints and the best scores below the logo.
+pub fn draw_code_menu(screen: &mut impl Screen, gsm: &GameStateManager) {
+    const INPUT_HEIGHT: usize = 19;
+    screen.plot_str("Enter a code:", 33, INPUT_HEIGHT, ColorCode::new(Color::White, Color::Black));
+    let settings = gsm.get_settings();
+    screen.clear_row(BUFFER_HEIGHT-1, Color::Black);
+    let hint_code = ColorCode::new(Color::DarkGray, Color::Black);
+    let col = screen.plot_str("Tab - theme: ", 0, BUFFER_HEIGHT-1, hint_code);
+    let col = screen.plot_str(settings.theme.name(), col, BUFFER_HEIGHT-1, hint_code);
+    let col = screen.plot_str("   F2 - display: ", col, BUFFER_HEIGHT-1, hint_code);
+    let col = screen.plot_str(display_name(settings.graphics), col, BUFFER_HEIGHT-1, hint_code);
+    if let Some(now) = gsm.get_clock() {
+        screen.plot_str(&format!("   F3 - daily: {}", now.date), col, BUFFER_HEIGHT-1, hint_code);
+    }
+    screen.clear_row(INPUT_HEIGHT+1, Color::Black);
+    let code = gsm.get_code();
+    let write_pos = (BUFFER_WIDTH - code.len()) / 2;
+    screen.plot_str(code, write_pos, INPUT_HEIGHT+1, ColorCode::new(Color::Yellow, Color::Black));
+    let versus = "F5 - play another machine over the serial link";
+    screen.plot_str(versus, (BUFFER_WIDTH - versus.len()) / 2, INPUT_HEIGHT-2, ColorCode::new(Color::DarkGray, Color::Black));
+    let hint = "Leave empty for a random board, or type a #hex seed to replay one";
+    screen.plot_str(hint, (BUFFER_WIDTH - hint.len()) / 2, INPUT_HEIGHT+2, ColorCode::new(Color::DarkGray, Color::Black));
+    screen.clear_row(INPUT_HEIGHT+3, Color::Black);
+    if let Some(best) = gsm.get_high_score() {
+        let msg = match best.date {
+            Some(date) => format!("High score: {} on {}", best.score, date),
+            None => format!("High score: {}", best.score),
+        };
+        screen.plot_str(&msg, (BUFFER_WIDTH - msg.len()) / 2, INPUT_HEIGHT+3, ColorCode::new(Color::LightGray, Color::Black));
+    }
+    screen.clear_row(INPUT_HEIGHT+4, Color::Black);
+    if let Some(best) = gsm.get_daily_best() {
+        let msg = format!("Today's daily best: {}", best.score);
+        screen.plot_str(&msg, (BUFFER_WIDTH - msg.len()) / 2, INPUT_HEIGHT+4, ColorCode::new(Color::LightGray, Color::Black));
+    }
+}
+
+fn display_name(graphics: bool) -> &'static str {
+    if graphics { "320x200" } else { "Text" }
+}
+
+const MENU_WIDTH: usize = 32;
+const MENU_COL: usize = (BUFFER_WIDTH - MENU_WIDTH) / 2;
+const MENU_ROW: usize = 7;
+
+/// Draws a framed menu with a title and one row per item, highlighting the selected row.
+/// Each item is a label and an optional value shown right-justified.
+fn draw_menu(screen: &mut impl Screen, title: &str, items: &[(&str, Option<&str>)], selected: usize) {
+    let frame_code = ColorCode::new(Color::White, Color::Blue);
+    let height = items.len() * 2 + 3;
+    for row in MENU_ROW..MENU_ROW + height {
+        screen.clear(MENU_WIDTH, MENU_COL, row, frame_code);
+    }
+    screen.plot_str(title, MENU_COL + (MENU_WIDTH - title.len()) / 2, MENU_ROW, ColorCode::new(Color::Yellow, Color::Blue));
+    for (i, (label, value)) in items.iter().enumerate() {
+        let row = MENU_ROW + 2 + i * 2;
+        let code = if i == selected {
+            ColorCode::new(Color::Black, Color::White)
+        } else {
+            frame_code
+        };
+        screen.clear(MENU_WIDTH - 4, MENU_COL + 2, row, code);
+        screen.plot_str(label, MENU_COL + 3, row, code);
+        if let Some(value) = value {
+            screen.plot_str(value, MENU_COL + MENU_WIDTH - 3 - value.len(), row, code);
+        }
+    }
+    let hint = "Arrows - move   Enter - choose   Esc - back";
+    screen.plot_str(hint, (BUFFER_WIDTH - hint.len()) / 2, MENU_ROW + height + 1, ColorCode::new(Color::DarkGray, Color::Black));
+}
+
+/// Draws the wait for an opponent on the serial link.
+pub fn draw_connecting(screen: &mut impl Screen, gsm: &GameStateManager) {
+    const WIDTH: usize = 44;
+    const COL: usize = (BUFFER_WIDTH - WIDTH) / 2;
+    let frame_code = ColorCode::new(Color::White, Color::Blue);
+    for row in MENU_ROW..MENU_ROW + 7 {
+        screen.clear(WIDTH, COL, row, frame_code);
+    }
+    let title = "Versus";
+    screen.plot_str(title, COL + (WIDTH - title.len()) / 2, MENU_ROW, ColorCode::new(Color::Yellow, Color::Blue));
+    let link = gsm.get_link();
+    let status = match link.state() {
+        LinkState::Host { .. } | LinkState::Guest => "Found an opponent, agreeing on a board",
+        _ => "Waiting for an opponent on COM2",
+    };
+    screen.plot_str(status, COL + 3, MENU_ROW + 2, frame_code);
+    if link.errors() > 0 {
+        screen.plot_str(&format!("Damaged frames: {}", link.errors()), COL + 3, MENU_ROW + 4, frame_code);
+    }
+    let hint = "Esc - cancel";
+    screen.plot_str(hint, (BUFFER_WIDTH - hint.len()) / 2, MENU_ROW + 8, ColorCode::new(Color::DarkGray, Color::Black));
+}
+
+pub fn draw_pause_menu(screen: &mut impl Screen, gsm: &GameStateManager) {
+    let mut items = [("", None); PauseOption::ALL.len()];
+    let mut selected = 0;
+    for (i, option) in PauseOption::ALL.iter().enumerate() {
+        items[i] = (option.label(), None);
+        if *option == gsm.get_pause_selection() { selected = i; }
+    }
+    draw_menu(screen, "Paused", &items, selected);
+}
+
+pub fn draw_settings_menu(screen: &mut impl Screen, gsm: &GameStateManager) {
+    let settings = gsm.get_settings();
+    let mut items = [("", None); SettingsOption::ALL.len()];
+    let mut selected = 0;
+    for (i, option) in SettingsOption::ALL.iter().enumerate() {
+        items[i] = match option {
+            SettingsOption::Theme => ("Theme", Some(settings.theme.name())),
+            SettingsOption::Display => ("Display", Some(display_name(settings.graphics))),
+            SettingsOption::Serial => ("Serial", Some(settings.serial.name())),
+            SettingsOption::Keyboard => ("Keyboard", Some(settings.layout.name())),
+            SettingsOption::Gems => ("Gems", Some(settings.gems.name())),
+            SettingsOption::Back => ("Back", None),
+        };
+        if *option == gsm.get_settings_selection() { selected = i; }
+    }
+    draw_menu(screen, "Settings", &items, selected);
+}
+
+/// Draws the board, the score and the side panels. **tick** times the blinking of a selected gem.
+pub fn draw_game(screen: &mut impl Screen, gsm: &GameStateManager, tick: u64) {
+    // board
+    const DRAW_COL_OFFSET: usize = 20;
+    const DRAW_ROW_OFFSET: usize = 0;
+    const SELECT_BLINK_PERIOD: u64 = 4;
+    let g = gsm.get_game();
+    let board = g.get_board();
+    let highlight_at = |col, row| if g.get_cursor().location() == (col, row) {
+        Color::DarkGray
+    } else {
+        Color::Black
+    };
+    // empty cells first, so that moving gems can be drawn between them
+    for col in 0..match3game::BOARD_WIDTH {
+        for row in 0..match3game::BOARD_HEIGHT {
+            let draw_col = col * 5 + DRAW_COL_OFFSET;
+            let draw_row = row * 3 + DRAW_ROW_OFFSET;
+            draw_empty(screen, draw_col, draw_row, highlight_at(col, row));
+            draw_empty_column(screen, draw_col + 4, draw_row, Color::Black);
+        }
+    }
+    for (col, column) in board.iter().enumerate() {
+        for (row, &current) in column.iter().enumerate() {
+            if current == 0 { continue; }
+            let (dx, dy) = g.get_offset(col, row);
+            let draw_col = (col * 5 + DRAW_COL_OFFSET) as isize + cell_shift(dx, 5);
+            let draw_row = (row * 3 + DRAW_ROW_OFFSET) as isize + cell_shift(dy, 3);
+            let stone = current == match3game::STONE;
+            let mut color = if stone {
+                Color::LightGray
+            } else {
+                Color::from(current + if g.is_alive() { 8 } else { 0 })
+            };
+            let selected = g.get_cursor().location() == (col, row)
+                && g.is_selected()
+                && tick % (SELECT_BLINK_PERIOD * 2) < SELECT_BLINK_PERIOD;
+            let mut center = if selected { '?' } else if stone { '#' } else { ' ' };
+            if let Some(ticks_left) = g.get_clearing(col, row) {
+                center = '*';
+                if ticks_left % 2 == 0 { color = Color::White; }
+            }
+            draw_gem(screen, draw_col as usize, draw_row, color, highlight_at(col, row), center);
+        }
+    }
+    // score popups, drawn last so they float over the gems until the next frame
+    for popup in g.get_popups() {
+        let points = popup.points as isize * 100;
+        let width = num_str_len(points) + 1;
+        let row = (popup.center.1 * 3 / 2) as isize - 1 - popup.age as isize / 2;
+        if row < 0 { continue; }
+        let center_col = DRAW_COL_OFFSET + popup.center.0 * 5 / 2;
+        let col = center_col.saturating_sub(width / 2)
+            .clamp(DRAW_COL_OFFSET, DRAW_COL_OFFSET + match3game::BOARD_WIDTH * 5 - 1 - width);
+        let popup_code = ColorCode::new(Color::Yellow, Color::Black);
+        screen.plot('+', col, row as usize, popup_code);
+        screen.plot_num(points, col + 1, row as usize, popup_code);
+    }
+    // score
+    let ui_code = ColorCode::new(Color::White, Color::DarkGray);
+    let msg = if g.is_alive() { "Score: " } else { "Game Over! Final Score:" };
+    screen.plot_num_right_justified(
+        match3game::BOARD_WIDTH*5-1-msg.len(),  // width of board in chars
+        g.get_score() as isize * 100,
+        DRAW_COL_OFFSET+msg.len(),              // start at end of msg
+        BUFFER_HEIGHT-1,                        // bottom row
+        ui_code
+    );
+    screen.plot_str(msg, DRAW_COL_OFFSET, BUFFER_HEIGHT-1, ui_code);
+    // high score on the left, clock on the right
+    const SIDE_WIDTH: usize = DRAW_COL_OFFSET - 2;
+    let side_code = ColorCode::new(Color::LightGray, Color::Black);
+    if let Some(best) = gsm.get_high_score() {
+        screen.plot_str("Best:", 1, 0, side_code);
+        screen.clear(SIDE_WIDTH - 1, 1, 1, side_code);
+        screen.plot_num(best.score as isize, 1, 1, side_code);
+        if let Some(date) = best.date {
+            screen.plot_str(&format!("{}", date), 1, 2, side_code);
+        }
+    }
+    if let Some(day) = gsm.get_daily() {
+        screen.plot_str(&format!("Daily {}", day), 1, 4, side_code);
+        if let Some(best) = gsm.get_daily_best() {
+            screen.plot_str("Daily best:", 1, 5, side_code);
+            screen.clear(SIDE_WIDTH - 1, 1, 6, side_code);
+            screen.plot_num(best.score as isize, 1, 6, side_code);
+        }
+    }
+    // the codes that replay this game
+    screen.plot_str("Seed:", 1, 8, side_code);
+    // a code with a gem policy suffix runs onto a second line
+    for (i, chunk) in gsm.get_seed_code().as_bytes().chunks(SIDE_WIDTH - 1).enumerate() {
+        screen.plot_str(core::str::from_utf8(chunk).unwrap_or(""), 1, 9 + i, side_code);
+    }
+    if let Some(code) = gsm.get_typed_code() {
+        screen.plot_str("Code:", 1, 11, side_code);
+        let shown = &code[..code.len().min(SIDE_WIDTH - 1)];
+        screen.plot_str(shown, 1, 12, side_code);
+    }
+    if let Some(now) = gsm.get_clock() {
+        let time = format!("{:02}:{:02}:{:02}", now.hour, now.minute, now.second);
+        let date = format!("{}", now.date);
+        screen.plot_str(&time, BUFFER_WIDTH - 1 - time.len(), 0, side_code);
+        screen.plot_str(&date, BUFFER_WIDTH - 1 - date.len(), 1, side_code);
+    }
+    // the opponent, in a versus game
+    if let Some(versus) = gsm.get_versus() {
+        const VERSUS_COL: usize = DRAW_COL_OFFSET + 41;
+        const VERSUS_WIDTH: usize = BUFFER_WIDTH - 1 - VERSUS_COL;
+        let lines = [
+            format!("Opponent: {}", versus.opponent_score * 100),
+            format!("Moves: {}", versus.opponent_moves),
+            format!("Lines sent: {}", versus.lines_sent),
+            format!("Lines got: {}", versus.lines_received),
+            format!("Rising: {}", g.get_pending_garbage()),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            screen.clear(VERSUS_WIDTH, VERSUS_COL, 3 + i, side_code);
+            screen.plot_str(line, VERSUS_COL, 3 + i, side_code);
+        }
+        screen.clear(VERSUS_WIDTH, VERSUS_COL, 8, side_code);
+        if versus.opponent_gone {
+            screen.plot_str("Opponent gone", VERSUS_COL, 8, side_code);
+        }
+        let (result, color) = match versus.outcome {
+            Some(Outcome::Won) => ("You win!", Color::LightGreen),
+            Some(Outcome::Lost) => ("You lose", Color::LightRed),
+            None => ("", Color::Black),
+        };
+        screen.clear(VERSUS_WIDTH, VERSUS_COL, 9, side_code);
+        screen.plot_str(result, VERSUS_COL, 9, ColorCode::new(color, Color::Black));
+    }
+    // outline
+    for row in 0..BUFFER_HEIGHT {
+        screen.plot(' ', DRAW_COL_OFFSET - 1, row, ui_code);
+        screen.plot(' ', DRAW_COL_OFFSET + 39, row, ui_code);
+    }
+}
+
+/// Converts a gem offset from Game::get_offset() into characters or pixels, given the size of a cell.
+pub fn cell_shift(offset: i8, cell_size: isize) -> isize {
+    offset as isize * cell_size / match3game::ANIMATION_STEPS as isize
+}
+
+/// Draws a gem with its top left corner at (c, r). Rows outside the board are skipped, so that
+/// gems can fall in from above it.
+fn draw_gem(screen: &mut impl Screen, c: usize, r: isize, color: Color, highlight: Color, center_char: char) {
+    let code = ColorCode::new(color, highlight);
+    let inverse_code = ColorCode::new(highlight, color);
+    let rows = [
+        [('/', code), ('-', code), ('-', code), ('\\', code)],
+        [('|', code), (center_char, inverse_code), (center_char, inverse_code), ('|', code)],
+        [('\\', code), ('-', code), ('-', code), ('/', code)],
+    ];
+    for (i, chars) in rows.iter().enumerate() {
+        let row = r + i as isize;
+        if row < 0 || row >= (match3game::BOARD_HEIGHT * 3) as isize { continue; }
+        for (j, (chr, code)) in chars.iter().enumerate() {
+            screen.plot(*chr, c + j, row as usize, *code);
+        }
+    }
+}
+fn draw_empty(screen: &mut impl Screen, c: usize, r: usize, color: Color) {
+    for c in c..c+4 {
+        draw_empty_column(screen, c, r, color);
+    }
+}
+fn draw_empty_column(screen: &mut impl Screen, c: usize, r: usize, color: Color) {
+    for r in r..r+3 {
+        screen.plot(' ', c, r, ColorCode::new(color, color));
+    }
+}
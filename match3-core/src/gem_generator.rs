@@ -0,0 +1,189 @@
+// Policies for choosing the gems that refill the board.
+//
+// Every column draws from its own generator stream, and each policy consumes a column's stream
+// the same way no matter what happens elsewhere on the board, so a seed and a policy together
+// always deal the same gems into each column. Only Mercy looks at the board, and then only to
+// weight its single draw.
+
+use crate::bitboard::Bitboards;
+use crate::match3game::{BOARD_HEIGHT, BOARD_WIDTH, GEM_COUNT};
+use crate::rng::{Rng, SeedableRng, Xoshiro256StarStar};
+
+/// The generator that deals gems. Any SeedableRng can be plugged in here.
+type GemRng = Xoshiro256StarStar;
+
+/// Relative chances of each color under the Weighted policy, from gem 1 to gem GEM_COUNT.
+const WEIGHTS: [u64; GEM_COUNT] = [4, 4, 3, 3, 2, 2, 1];
+/// How much more likely Mercy makes a color that leaves the player a legal move.
+const MERCY_WEIGHT: u64 = 4;
+
+/// How refill gems are chosen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GemPolicy {
+    /// Every color is equally likely on every draw.
+    Uniform,
+    /// Each column deals all the colors once, in shuffled order, before dealing any twice. This
+    /// avoids long droughts and streaks of one color.
+    Bag,
+    /// Some colors are more common than others.
+    Weighted,
+    /// Like Uniform, but colors that give the player a legal move are more likely, which makes
+    /// dead boards rarer.
+    Mercy,
+}
+
+impl GemPolicy {
+    pub const ALL: [GemPolicy; 4] = [GemPolicy::Uniform, GemPolicy::Bag, GemPolicy::Weighted, GemPolicy::Mercy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GemPolicy::Uniform => "Uniform",
+            GemPolicy::Bag => "7-bag",
+            GemPolicy::Weighted => "Weighted",
+            GemPolicy::Mercy => "Mercy",
+        }
+    }
+
+    /// A letter for this policy in seed codes.
+    pub fn letter(self) -> char {
+        match self {
+            GemPolicy::Uniform => 'u',
+            GemPolicy::Bag => 'b',
+            GemPolicy::Weighted => 'w',
+            GemPolicy::Mercy => 'm',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        Self::ALL.iter().copied().find(|policy| policy.letter() == letter.to_ascii_lowercase())
+    }
+
+    /// Returns the policy after this one, wrapping around to the first.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|p| *p == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// The shuffled colors a column has yet to deal under the Bag policy.
+#[derive(Clone, Copy)]
+struct Bag {
+    gems: [u8; GEM_COUNT],
+    remaining: usize,
+}
+
+#[derive(Clone)]
+pub struct GemGenerator {
+    policy: GemPolicy,
+    rngs: [GemRng; BOARD_WIDTH],
+    bags: [Bag; BOARD_WIDTH],
+}
+
+impl GemGenerator {
+    pub fn new(policy: GemPolicy, seed: u64) -> Self {
+        GemGenerator {
+            policy,
+            rngs: core::array::from_fn(|col| GemRng::stream(seed, col as u64)),
+            bags: [Bag { gems: [0; GEM_COUNT], remaining: 0 }; BOARD_WIDTH],
+        }
+    }
+
+    /// Chooses the gem to drop into the top of **col**, numbered from 1.
+    pub fn next(&mut self, col: usize, board: &[[u8; BOARD_HEIGHT]; BOARD_WIDTH]) -> u8 {
+        let rng = &mut self.rngs[col];
+        match self.policy {
+            GemPolicy::Uniform => rng.range(0, GEM_COUNT as u64) as u8 + 1,
+            GemPolicy::Bag => {
+                let bag = &mut self.bags[col];
+                if bag.remaining == 0 {
+                    refill(bag, rng);
+                }
+                bag.remaining -= 1;
+                bag.gems[bag.remaining]
+            }
+            GemPolicy::Weighted => pick_weighted(rng, &WEIGHTS),
+            GemPolicy::Mercy => {
+                // the gem falls until it rests on the gems below it, so judge it there
+                let row = board[col].iter().filter(|gem| **gem == 0).count().saturating_sub(1);
+                let mut weights = [1; GEM_COUNT];
+                let mut boards = Bitboards::from_board(board);
+                for (gem, weight) in weights.iter_mut().enumerate() {
+                    boards.place(col, row, gem as u8 + 1);
+                    if boards.has_legal_move() {
+                        *weight = MERCY_WEIGHT;
+                    }
+                }
+                pick_weighted(rng, &weights)
+            }
+        }
+    }
+}
+
+/// Puts one of each color into **bag** and shuffles them (Fisher-Yates).
+fn refill(bag: &mut Bag, rng: &mut GemRng) {
+    for (i, gem) in bag.gems.iter_mut().enumerate() {
+        *gem = i as u8 + 1;
+    }
+    for i in (1..GEM_COUNT).rev() {
+        let j = rng.range(0, i as u64 + 1) as usize;
+        bag.gems.swap(i, j);
+    }
+    bag.remaining = GEM_COUNT;
+}
+
+/// Picks a gem with one draw, where gem i + 1 has chance weights[i] / sum(weights).
+fn pick_weighted(rng: &mut GemRng, weights: &[u64; GEM_COUNT]) -> u8 {
+    let mut roll = rng.range(0, weights.iter().sum());
+    for (gem, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return gem as u8 + 1;
+        }
+        roll -= weight;
+    }
+    GEM_COUNT as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIALS: u64 = 2000;
+
+    /// Clears the top of one column of a board with no legal move, drops a gem from **policy**
+    /// to the bottom of the gap, and counts how often the board is still dead. The boards are
+    /// diagonal stripes of shuffled colors, which never have a legal move.
+    fn dead_after_refill(policy: GemPolicy) -> u64 {
+        let mut rng = Xoshiro256StarStar::from_seed(320);
+        let mut dead = 0;
+        for trial in 0..TRIALS {
+            let mut colors = [1, 2, 3, 4, 5, 6, 7];
+            for i in (1..GEM_COUNT).rev() {
+                colors.swap(i, rng.range(0, i as u64 + 1) as usize);
+            }
+            let mut board = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+            for (col, column) in board.iter_mut().enumerate() {
+                for (row, gem) in column.iter_mut().enumerate() {
+                    *gem = colors[(col + row) % GEM_COUNT];
+                }
+            }
+            let col = rng.range(0, BOARD_WIDTH as u64) as usize;
+            let cleared = rng.range(1, 4) as usize;
+            for gem in &mut board[col][..cleared] {
+                *gem = 0;
+            }
+            let mut gems = GemGenerator::new(policy, trial);
+            board[col][cleared - 1] = gems.next(col, &board);
+            if !Bitboards::from_board(&board).has_legal_move() {
+                dead += 1;
+            }
+        }
+        dead
+    }
+
+    #[test]
+    fn mercy_leaves_fewer_dead_boards() {
+        let uniform = dead_after_refill(GemPolicy::Uniform);
+        let mercy = dead_after_refill(GemPolicy::Mercy);
+        assert!(mercy < uniform * 3 / 4, "Mercy left {} dead boards, Uniform {}", mercy, uniform);
+    }
+}
@@ -0,0 +1,80 @@
+// Keyboard types shared by the game and the kernel's keyboard driver.
+//
+// The kernel decodes scancodes with one of these layouts and reports every key going down or up
+// as a RawKeyEvent, which the game uses to auto-repeat held arrow keys. Other frontends build the
+// same events from their own input.
+
+use pc_keyboard::{KeyCode, KeyState};
+
+/// The keyboard layouts that scancodes can be decoded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    Us104Key,
+    Uk105Key,
+    Jis109Key,
+    Azerty,
+    Dvorak104Key,
+}
+
+impl KeyboardLayout {
+    pub const ALL: [KeyboardLayout; 5] = [
+        KeyboardLayout::Us104Key, KeyboardLayout::Uk105Key, KeyboardLayout::Jis109Key,
+        KeyboardLayout::Azerty, KeyboardLayout::Dvorak104Key
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLayout::Us104Key => "US 104-key",
+            KeyboardLayout::Uk105Key => "UK 105-key",
+            KeyboardLayout::Jis109Key => "JIS 109-key",
+            KeyboardLayout::Azerty => "AZERTY",
+            KeyboardLayout::Dvorak104Key => "Dvorak 104-key",
+        }
+    }
+}
+
+/// Which modifier keys are held down, and whether caps lock is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifiers {
+    pub lshift: bool,
+    pub rshift: bool,
+    pub lctrl: bool,
+    pub rctrl: bool,
+    pub lalt: bool,
+    pub ralt: bool,
+    pub caps_lock: bool,
+}
+
+impl Modifiers {
+    pub fn shift(&self) -> bool {
+        self.lshift || self.rshift
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.lctrl || self.rctrl
+    }
+
+    pub fn alt(&self) -> bool {
+        self.lalt || self.ralt
+    }
+}
+
+/// A key going down or coming back up, before it is decoded into a character. Holding a key
+/// down makes the keyboard repeat its **Down** event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawKeyEvent {
+    pub code: KeyCode,
+    pub state: KeyState,
+    /// The modifiers in effect after this event.
+    pub modifiers: Modifiers,
+}
+
+impl RawKeyEvent {
+    pub fn is_press(&self) -> bool {
+        self.state == KeyState::Down
+    }
+
+    pub fn is_release(&self) -> bool {
+        self.state == KeyState::Up
+    }
+}
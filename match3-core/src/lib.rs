@@ -0,0 +1,24 @@
+//! The match-3 game, independent of how it is drawn and where its input comes from.
+//!
+//! The kernel in `kernel/` runs it on bare metal, drawing through the VGA buffer and reading
+//! the PS/2 keyboard. `match3-term` runs the same game in a terminal on the host. Both feed it
+//! timer ticks and keys through **GameStateManager**, and draw the screens in **draw** on
+//! anything that implements **screen::Screen**. Two kernels can play each other over a serial
+//! link with the protocol in **link**.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod ansi;
+pub mod bitboard;
+pub mod date;
+pub mod draw;
+pub mod gem_generator;
+pub mod input;
+pub mod link;
+pub mod match3game;
+pub mod rng;
+pub mod screen;
+pub mod terminal_keys;
+pub mod theme;
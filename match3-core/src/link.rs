@@ -0,0 +1,680 @@
+// A framed, checksummed protocol for two kernels playing against each other over a null-modem
+// serial link, such as two QEMU instances whose COM2 ports are joined by a socket.
+//
+// Each message is sent as one frame:
+//     START kind sequence-low sequence-high length payload... checksum-high checksum-low
+// START marks where a frame begins, so a receiver that starts listening partway through a frame,
+// or loses a byte of one, skips ahead to the next. The checksum is Fletcher-16 over the kind,
+// the sequence number, the length and the payload, and a frame whose checksum is wrong is
+// dropped and counted.
+//
+// Before a game, each side sends Hello with the protocol version and a random nonce every
+// RESEND_PERIOD ticks until it hears the other's. The side with the larger nonce is the host: it
+// sends the seed in Seed until the guest echoes it back, and then both play the same board. If
+// the nonces are equal, both sides pick new ones and try again.
+//
+// During the game only swaps, scores, garbage and the end of the game are sent. They are numbered
+// from 1, and the other side answers each with an Ack of the last number it has in order.
+// Whatever has not been acknowledged is sent again every RESEND_PERIOD ticks, and a repeat of a
+// message that already arrived is acknowledged but not passed on, so a dropped frame only delays
+// the messages after it. The Ack is repeated every RESEND_PERIOD ticks too, so an opponent that
+// sends nothing for SILENCE_LIMIT ticks has gone. Handshake frames and acks are numbered 0.
+
+use alloc::vec::Vec;
+use crate::match3game::{Direction, Move, BOARD_HEIGHT, BOARD_WIDTH};
+
+/// Bumped whenever a message changes, so that mismatched kernels never start a game.
+pub const VERSION: u8 = 1;
+/// The first byte of every frame.
+const START: u8 = 0x7e;
+/// The longest payload of any message.
+const MAX_PAYLOAD: usize = 9;
+/// Ticks between repeats of unanswered messages, about half a second.
+pub const RESEND_PERIOD: u64 = 9;
+/// Ticks without a frame from the opponent during a game before it is taken to have gone,
+/// about five seconds.
+pub const SILENCE_LIMIT: u64 = 10 * RESEND_PERIOD;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    /// Looking for an opponent.
+    Hello { version: u8, nonce: u64 },
+    /// The host's choice of seed, or the guest's echo of it.
+    Seed(u64),
+    /// A swap that was accepted.
+    Swap(Move),
+    /// The sender's score, in the game's units rather than the hundreds it is shown in.
+    Score(u32),
+    /// Lines of garbage for the receiver's board.
+    Garbage(u8),
+    /// The sender's game ended with this score, because its board ran out of moves or its player
+    /// left.
+    GameOver(u32),
+    /// Every numbered message up to this number has arrived.
+    Ack(u16),
+}
+
+impl Message {
+    fn kind(&self) -> u8 {
+        match self {
+            Message::Hello { .. } => 1,
+            Message::Seed(_) => 2,
+            Message::Swap(_) => 3,
+            Message::Score(_) => 4,
+            Message::Garbage(_) => 5,
+            Message::GameOver(_) => 6,
+            Message::Ack(_) => 7,
+        }
+    }
+
+    /// Whether this message is numbered and acknowledged: the messages sent during a game.
+    fn numbered(&self) -> bool {
+        matches!(self, Message::Swap(_) | Message::Score(_) | Message::Garbage(_) | Message::GameOver(_))
+    }
+
+    /// Appends the frame that carries this message, numbered **seq**, to **out**.
+    pub fn encode(&self, seq: u16, out: &mut Vec<u8>) {
+        let mut payload = Vec::new();
+        match *self {
+            Message::Hello { version, nonce } => {
+                payload.push(version);
+                payload.extend_from_slice(&nonce.to_le_bytes());
+            },
+            Message::Seed(seed) => payload.extend_from_slice(&seed.to_le_bytes()),
+            Message::Swap(mv) => payload.extend_from_slice(&[mv.col as u8, mv.row as u8, direction_byte(mv.dir)]),
+            Message::Score(score) | Message::GameOver(score) => payload.extend_from_slice(&score.to_le_bytes()),
+            Message::Garbage(lines) => payload.push(lines),
+            Message::Ack(seq) => payload.extend_from_slice(&seq.to_le_bytes()),
+        }
+        let [seq_low, seq_high] = seq.to_le_bytes();
+        let header = [self.kind(), seq_low, seq_high, payload.len() as u8];
+        let checksum = fletcher16(header.iter().chain(payload.iter()));
+        out.push(START);
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(&checksum.to_be_bytes());
+    }
+
+    /// Reads the message of **kind** from **payload**, if it is one.
+    fn decode(kind: u8, payload: &[u8]) -> Option<Message> {
+        let u32_at = |i: usize| Some(u32::from_le_bytes(payload.get(i..i + 4)?.try_into().ok()?));
+        let u64_at = |i: usize| Some(u64::from_le_bytes(payload.get(i..i + 8)?.try_into().ok()?));
+        let message = match (kind, payload.len()) {
+            (1, 9) => Message::Hello { version: payload[0], nonce: u64_at(1)? },
+            (2, 8) => Message::Seed(u64_at(0)?),
+            (3, 3) => {
+                let mv = Move { col: payload[0] as usize, row: payload[1] as usize, dir: byte_direction(payload[2])? };
+                // a swap off the board can only come from a corrupted or foreign frame
+                if mv.col >= BOARD_WIDTH || mv.row >= BOARD_HEIGHT { return None; }
+                mv.other()?;
+                Message::Swap(mv)
+            },
+            (4, 4) => Message::Score(u32_at(0)?),
+            (5, 1) => Message::Garbage(payload[0]),
+            (6, 4) => Message::GameOver(u32_at(0)?),
+            (7, 2) => Message::Ack(u16::from_le_bytes([payload[0], payload[1]])),
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+fn direction_byte(dir: Direction) -> u8 {
+    match dir {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    }
+}
+
+fn byte_direction(byte: u8) -> Option<Direction> {
+    [Direction::Up, Direction::Down, Direction::Left, Direction::Right].get(byte as usize).copied()
+}
+
+/// The Fletcher-16 checksum of **bytes**.
+fn fletcher16<'a>(bytes: impl Iterator<Item = &'a u8>) -> u16 {
+    let (mut low, mut high) = (0u16, 0u16);
+    for byte in bytes {
+        low = (low + *byte as u16) % 255;
+        high = (high + low) % 255;
+    }
+    high << 8 | low
+}
+
+/// Where a frame being received has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameState { Start, Kind, Sequence, Length, Payload, Checksum }
+
+/// Reassembles frames from the bytes that arrive, one at a time.
+#[derive(Debug)]
+pub struct Receiver {
+    state: FrameState,
+    kind: u8,
+    seq: [u8; 2],
+    payload: [u8; MAX_PAYLOAD],
+    len: usize,
+    received: usize,
+    checksum: [u8; 2],
+    /// Frames dropped for a bad length, checksum or payload.
+    errors: usize,
+}
+
+impl Receiver {
+    pub fn new() -> Self {
+        Receiver { state: FrameState::Start, kind: 0, seq: [0; 2], payload: [0; MAX_PAYLOAD], len: 0, received: 0, checksum: [0; 2], errors: 0 }
+    }
+
+    /// Takes in **byte**, and returns the message it completes, if any, with its number.
+    pub fn push(&mut self, byte: u8) -> Option<(u16, Message)> {
+        match self.state {
+            FrameState::Start => if byte == START {
+                self.state = FrameState::Kind;
+            },
+            FrameState::Kind => {
+                self.kind = byte;
+                self.received = 0;
+                self.state = FrameState::Sequence;
+            },
+            FrameState::Sequence => {
+                self.seq[self.received] = byte;
+                self.received += 1;
+                if self.received == self.seq.len() {
+                    self.state = FrameState::Length;
+                }
+            },
+            FrameState::Length => {
+                self.len = byte as usize;
+                self.received = 0;
+                self.state = if self.len > MAX_PAYLOAD {
+                    self.errors += 1;
+                    FrameState::Start
+                } else if self.len == 0 {
+                    FrameState::Checksum
+                } else {
+                    FrameState::Payload
+                };
+            },
+            FrameState::Payload => {
+                self.payload[self.received] = byte;
+                self.received += 1;
+                if self.received == self.len {
+                    self.received = 0;
+                    self.state = FrameState::Checksum;
+                }
+            },
+            FrameState::Checksum => {
+                self.checksum[self.received] = byte;
+                self.received += 1;
+                if self.received == self.checksum.len() {
+                    self.state = FrameState::Start;
+                    return self.finish();
+                }
+            },
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Option<(u16, Message)> {
+        let header = [self.kind, self.seq[0], self.seq[1], self.len as u8];
+        let payload = &self.payload[..self.len];
+        let message = if fletcher16(header.iter().chain(payload.iter())) == u16::from_be_bytes(self.checksum) {
+            Message::decode(self.kind, payload)
+        } else {
+            None
+        };
+        if message.is_none() {
+            self.errors += 1;
+        }
+        Some((u16::from_le_bytes(self.seq), message?))
+    }
+
+    /// Returns how many frames have been dropped.
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+}
+
+impl Default for Receiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How far the link has got toward a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// Not looking for an opponent.
+    Idle,
+    /// Sending Hello and waiting for the other side's.
+    Hello,
+    /// The other side will choose the seed.
+    Guest,
+    /// Sending **seed** and waiting for the guest to echo it.
+    Host { seed: u64 },
+    /// Both sides have the seed, and a game is on. **host** is whether this side chose it.
+    Playing { seed: u64, host: bool },
+    /// This side left the game, and is still sending what the opponent has not acknowledged,
+    /// such as its GameOver.
+    Closing,
+}
+
+/// What a received byte or a tick meant for the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// The handshake finished, and both sides should start a game with this seed.
+    Start(u64),
+    /// A message from the opponent during the game.
+    Message(Message),
+    /// The opponent has sent nothing for SILENCE_LIMIT ticks during the game, and the link is
+    /// closed.
+    Lost,
+}
+
+/// One end of the link: the handshake, and the frames waiting to be sent.
+#[derive(Debug)]
+pub struct Link {
+    state: LinkState,
+    nonce: u64,
+    /// Where a new nonce comes from when both sides chose the same one.
+    entropy: fn() -> u64,
+    /// The seed this side offers if it turns out to be the host.
+    offered_seed: u64,
+    receiver: Receiver,
+    outbox: Vec<u8>,
+    ticks_to_resend: u64,
+    /// Messages sent during the game that the opponent has not acknowledged, oldest first.
+    unacked: Vec<(u16, Message)>,
+    /// The number of the next message sent during the game.
+    next_seq: u16,
+    /// The number of the next message expected from the opponent.
+    expected_seq: u16,
+    /// Ticks since the last frame from the opponent.
+    silent_ticks: u64,
+}
+
+impl Link {
+    pub fn new() -> Self {
+        Link {
+            state: LinkState::Idle, nonce: 0, entropy: || 0, offered_seed: 0, receiver: Receiver::new(), outbox: Vec::new(),
+            ticks_to_resend: 0, unacked: Vec::new(), next_seq: 1, expected_seq: 1, silent_ticks: 0
+        }
+    }
+
+    /// Starts looking for an opponent. **entropy** chooses the nonce that decides which side is
+    /// the host, so it should be random; **seed** is played if this side is.
+    pub fn connect(&mut self, entropy: fn() -> u64, seed: u64) {
+        self.state = LinkState::Hello;
+        self.entropy = entropy;
+        self.nonce = entropy();
+        self.offered_seed = seed;
+        self.resend();
+    }
+
+    /// Stops looking for an opponent, or leaves the game. Frames already queued are still sent,
+    /// and so are the messages of the game until the opponent acknowledges them or goes silent.
+    pub fn disconnect(&mut self) {
+        self.state = match self.state {
+            LinkState::Playing { .. } | LinkState::Closing if !self.unacked.is_empty() => LinkState::Closing,
+            _ => LinkState::Idle,
+        };
+    }
+
+    pub fn state(&self) -> LinkState {
+        self.state
+    }
+
+    /// Returns how many frames from the other side have been dropped.
+    pub fn errors(&self) -> usize {
+        self.receiver.errors()
+    }
+
+    /// Call once per timer tick, to repeat the messages that have not been answered. Returns
+    /// LinkEvent::Lost once the opponent has been silent for too long during a game.
+    pub fn tick(&mut self) -> Option<LinkEvent> {
+        match self.state {
+            LinkState::Idle => return None,
+            LinkState::Playing { .. } | LinkState::Closing => {
+                self.silent_ticks += 1;
+                if self.silent_ticks >= SILENCE_LIMIT {
+                    let playing = self.state != LinkState::Closing;
+                    self.state = LinkState::Idle;
+                    return playing.then_some(LinkEvent::Lost);
+                }
+            },
+            LinkState::Hello | LinkState::Guest | LinkState::Host { .. } => {},
+        }
+        self.ticks_to_resend = self.ticks_to_resend.saturating_sub(1);
+        if self.ticks_to_resend == 0 {
+            self.resend();
+        }
+        None
+    }
+
+    fn resend(&mut self) {
+        self.ticks_to_resend = RESEND_PERIOD;
+        match self.state {
+            LinkState::Hello | LinkState::Guest => self.queue(Message::Hello { version: VERSION, nonce: self.nonce }),
+            LinkState::Host { seed } => self.queue(Message::Seed(seed)),
+            LinkState::Playing { .. } | LinkState::Closing => {
+                // the ack also tells the opponent that this side is still there
+                self.queue(Message::Ack(self.expected_seq.wrapping_sub(1)));
+                for (seq, message) in &self.unacked {
+                    message.encode(*seq, &mut self.outbox);
+                }
+            },
+            LinkState::Idle => {},
+        }
+    }
+
+    fn start_playing(&mut self, seed: u64, host: bool) {
+        self.state = LinkState::Playing { seed, host };
+        self.unacked.clear();
+        self.next_seq = 1;
+        self.expected_seq = 1;
+        self.silent_ticks = 0;
+        self.ticks_to_resend = RESEND_PERIOD;
+    }
+
+    /// Sends **message**, one of the game's messages, to the opponent if a game is on. It is
+    /// sent again until the opponent acknowledges it.
+    pub fn send(&mut self, message: Message) {
+        if matches!(self.state, LinkState::Playing { .. }) {
+            let seq = self.next_seq;
+            self.next_seq = seq.wrapping_add(1);
+            self.unacked.push((seq, message));
+            message.encode(seq, &mut self.outbox);
+        }
+    }
+
+    /// Queues **message** unnumbered, as handshake messages and acks are.
+    fn queue(&mut self, message: Message) {
+        message.encode(0, &mut self.outbox);
+    }
+
+    /// Returns the bytes waiting to be sent, and forgets them.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.outbox)
+    }
+
+    /// Takes in a byte from the other side, and returns what it meant for the game, if anything.
+    pub fn receive(&mut self, byte: u8) -> Option<LinkEvent> {
+        let (seq, message) = self.receiver.push(byte)?;
+        self.silent_ticks = 0;
+        match (self.state, message) {
+            (LinkState::Hello, Message::Hello { version: VERSION, nonce }) => {
+                if nonce > self.nonce {
+                    self.state = LinkState::Guest;
+                    // answer at once, in case the host had not heard this side yet
+                    self.resend();
+                } else if nonce < self.nonce {
+                    self.state = LinkState::Host { seed: self.offered_seed };
+                    self.resend();
+                } else {
+                    // equal nonces cannot choose a host, so both sides choose again
+                    self.nonce = (self.entropy)();
+                    self.resend();
+                }
+                None
+            },
+            (LinkState::Hello | LinkState::Guest, Message::Seed(seed)) => {
+                self.start_playing(seed, false);
+                self.queue(Message::Seed(seed));
+                Some(LinkEvent::Start(seed))
+            },
+            (LinkState::Host { seed }, Message::Seed(echo)) if echo == seed => {
+                self.start_playing(seed, true);
+                Some(LinkEvent::Start(seed))
+            },
+            (LinkState::Playing { seed, host: false }, Message::Seed(echo)) => {
+                // the host missed the echo and is still sending the seed. Only the guest answers,
+                // so that a late echo reaching the host is not sent back and forth forever.
+                if echo == seed {
+                    self.queue(Message::Seed(seed));
+                }
+                None
+            },
+            (LinkState::Playing { .. } | LinkState::Closing, Message::Ack(acked)) => {
+                // numbers wrap around, so "up to" means within half the range behind
+                self.unacked.retain(|(sent, _)| acked.wrapping_sub(*sent) >= 0x8000);
+                if self.state == LinkState::Closing && self.unacked.is_empty() {
+                    self.state = LinkState::Idle;
+                }
+                None
+            },
+            (LinkState::Playing { .. } | LinkState::Closing, message) if message.numbered() => {
+                // a message after a lost one is dropped too, and arrives again in order
+                let next = seq == self.expected_seq;
+                if next {
+                    self.expected_seq = seq.wrapping_add(1);
+                }
+                // repeats are acknowledged again, in case the first ack was lost
+                self.queue(Message::Ack(self.expected_seq.wrapping_sub(1)));
+                let playing = matches!(self.state, LinkState::Playing { .. });
+                (next && playing).then_some(LinkEvent::Message(message))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(message: Message) -> Vec<u8> {
+        let mut out = Vec::new();
+        message.encode(0, &mut out);
+        out
+    }
+
+    fn receive_all(receiver: &mut Receiver, bytes: &[u8]) -> Vec<Message> {
+        bytes.iter().filter_map(|byte| receiver.push(*byte)).map(|(_, message)| message).collect()
+    }
+
+    /// Two links that have finished the handshake, the first one the guest.
+    fn playing() -> (Link, Link) {
+        let (mut guest, mut host) = (Link::new(), Link::new());
+        guest.connect(|| 1, 0xaaaa);
+        host.connect(|| 2, 0xbbbb);
+        exchange(&mut guest, &mut host);
+        (guest, host)
+    }
+
+    /// Passes everything each side has queued to the other, until neither has anything to say.
+    fn exchange(a: &mut Link, b: &mut Link) -> (Vec<LinkEvent>, Vec<LinkEvent>) {
+        let (mut a_events, mut b_events) = (Vec::new(), Vec::new());
+        for _ in 0..16 {
+            let (to_b, to_a) = (a.take_output(), b.take_output());
+            if to_a.is_empty() && to_b.is_empty() {
+                return (a_events, b_events);
+            }
+            b_events.extend(to_b.iter().filter_map(|byte| b.receive(*byte)));
+            a_events.extend(to_a.iter().filter_map(|byte| a.receive(*byte)));
+        }
+        panic!("the link never went quiet");
+    }
+
+    #[test]
+    fn messages_survive_framing() {
+        let messages = [
+            Message::Hello { version: VERSION, nonce: 0x7e7e_0000_ffff_1234 },
+            Message::Seed(u64::MAX),
+            Message::Swap(Move { col: 7, row: 0, dir: Direction::Left }),
+            Message::Score(123_456),
+            Message::Garbage(2),
+            Message::GameOver(0),
+            Message::Ack(0xfffe),
+        ];
+        let bytes: Vec<u8> = messages.iter().flat_map(|message| frame(*message)).collect();
+        let mut receiver = Receiver::new();
+        assert_eq!(receive_all(&mut receiver, &bytes), messages);
+        assert_eq!(receiver.errors(), 0);
+    }
+
+    #[test]
+    fn corrupted_frames_are_dropped() {
+        let mut bytes = frame(Message::Score(42));
+        bytes[6] ^= 0x10;
+        // garbage between frames, then a good one
+        bytes.extend_from_slice(&[0x00, 0x13]);
+        bytes.extend(frame(Message::Garbage(1)));
+        let mut receiver = Receiver::new();
+        assert_eq!(receive_all(&mut receiver, &bytes), [Message::Garbage(1)]);
+        assert_eq!(receiver.errors(), 1);
+        // a swap off the board has a good checksum but is still refused
+        let mut off_board = frame(Message::Swap(Move { col: 0, row: 0, dir: Direction::Left }));
+        off_board.extend(frame(Message::Garbage(3)));
+        assert_eq!(receive_all(&mut receiver, &off_board), [Message::Garbage(3)]);
+        assert_eq!(receiver.errors(), 2);
+    }
+
+    #[test]
+    fn handshake_agrees_on_the_host_seed() {
+        let (mut a, mut b) = (Link::new(), Link::new());
+        a.connect(|| 1, 0xaaaa);
+        b.connect(|| 2, 0xbbbb);
+        let (a_events, b_events) = exchange(&mut a, &mut b);
+        assert_eq!(a_events, [LinkEvent::Start(0xbbbb)]);
+        assert_eq!(b_events, [LinkEvent::Start(0xbbbb)]);
+        assert_eq!(a.state(), LinkState::Playing { seed: 0xbbbb, host: false });
+        assert_eq!(b.state(), LinkState::Playing { seed: 0xbbbb, host: true });
+        a.send(Message::Garbage(1));
+        let (_, b_events) = exchange(&mut a, &mut b);
+        assert_eq!(b_events, [LinkEvent::Message(Message::Garbage(1))]);
+    }
+
+    #[test]
+    fn handshake_waits_for_a_late_opponent() {
+        let (mut a, mut b) = (Link::new(), Link::new());
+        a.connect(|| 9, 0xaaaa);
+        // b is not looking for an opponent yet
+        let (a_events, b_events) = exchange(&mut a, &mut b);
+        assert!(a_events.is_empty() && b_events.is_empty());
+        assert_eq!(b.state(), LinkState::Idle);
+        // and its first Hello is lost
+        b.connect(|| 3, 0xbbbb);
+        b.take_output();
+        for _ in 0..RESEND_PERIOD {
+            a.tick();
+        }
+        let (a_events, b_events) = exchange(&mut a, &mut b);
+        assert_eq!(a_events, [LinkEvent::Start(0xaaaa)]);
+        assert_eq!(b_events, [LinkEvent::Start(0xaaaa)]);
+    }
+
+    #[test]
+    fn resent_seed_crossing_the_echo_settles() {
+        let (mut guest, mut host) = (Link::new(), Link::new());
+        guest.connect(|| 1, 0xaaaa);
+        host.connect(|| 2, 0xbbbb);
+        for byte in guest.take_output() {
+            host.receive(byte);
+        }
+        // the guest hears Hello and Seed together, and echoes the seed
+        let events: Vec<LinkEvent> = host.take_output().iter().filter_map(|byte| guest.receive(*byte)).collect();
+        assert_eq!(events, [LinkEvent::Start(0xbbbb)]);
+        // but the host resends the seed before the echo arrives
+        for _ in 0..RESEND_PERIOD {
+            host.tick();
+        }
+        let (guest_events, host_events) = exchange(&mut guest, &mut host);
+        assert!(guest_events.is_empty());
+        assert_eq!(host_events, [LinkEvent::Start(0xbbbb)]);
+        assert_eq!(host.state(), LinkState::Playing { seed: 0xbbbb, host: true });
+    }
+
+    #[test]
+    fn equal_nonces_choose_again() {
+        use core::sync::atomic::{AtomicU64, Ordering};
+        static NEXT: AtomicU64 = AtomicU64::new(5);
+        let (mut a, mut b) = (Link::new(), Link::new());
+        a.connect(|| 5, 0xaaaa);
+        b.connect(|| NEXT.fetch_add(1, Ordering::Relaxed), 0xbbbb);
+        let (a_events, b_events) = exchange(&mut a, &mut b);
+        assert_eq!(a_events, [LinkEvent::Start(0xbbbb)]);
+        assert_eq!(b_events, [LinkEvent::Start(0xbbbb)]);
+    }
+
+    #[test]
+    fn lost_messages_are_resent_in_order() {
+        let (mut guest, mut host) = playing();
+        guest.send(Message::Garbage(2));
+        guest.take_output();
+        // the next message arrives first, and waits for the lost one
+        guest.send(Message::GameOver(5));
+        let (_, host_events) = exchange(&mut guest, &mut host);
+        assert!(host_events.is_empty());
+        for _ in 0..RESEND_PERIOD {
+            guest.tick();
+        }
+        let (_, host_events) = exchange(&mut guest, &mut host);
+        assert_eq!(host_events, [LinkEvent::Message(Message::Garbage(2)), LinkEvent::Message(Message::GameOver(5))]);
+        // once acknowledged, they are not sent again
+        for _ in 0..RESEND_PERIOD {
+            guest.tick();
+        }
+        let (_, host_events) = exchange(&mut guest, &mut host);
+        assert!(host_events.is_empty());
+    }
+
+    #[test]
+    fn repeats_arrive_once() {
+        let (mut guest, mut host) = playing();
+        host.send(Message::Score(7));
+        let events: Vec<LinkEvent> = host.take_output().iter().filter_map(|byte| guest.receive(*byte)).collect();
+        assert_eq!(events, [LinkEvent::Message(Message::Score(7))]);
+        // the ack is lost, so the host sends the score again
+        guest.take_output();
+        for _ in 0..RESEND_PERIOD {
+            host.tick();
+        }
+        let (guest_events, _) = exchange(&mut guest, &mut host);
+        assert!(guest_events.is_empty());
+    }
+
+    #[test]
+    fn leaving_still_delivers_the_game_over() {
+        let (mut guest, mut host) = playing();
+        guest.send(Message::GameOver(3));
+        guest.take_output();
+        guest.disconnect();
+        assert_eq!(guest.state(), LinkState::Closing);
+        for _ in 0..RESEND_PERIOD {
+            guest.tick();
+        }
+        let (_, host_events) = exchange(&mut guest, &mut host);
+        assert_eq!(host_events, [LinkEvent::Message(Message::GameOver(3))]);
+        assert_eq!(guest.state(), LinkState::Idle);
+    }
+
+    #[test]
+    fn silent_opponents_are_lost() {
+        let (mut guest, mut host) = playing();
+        // hearing from the opponent keeps the game going
+        for _ in 0..SILENCE_LIMIT {
+            assert_eq!(guest.tick(), None);
+            host.tick();
+            exchange(&mut guest, &mut host);
+        }
+        let events: Vec<Option<LinkEvent>> = (0..SILENCE_LIMIT).map(|_| guest.tick()).collect();
+        assert_eq!(events.iter().flatten().collect::<Vec<_>>(), [&LinkEvent::Lost]);
+        assert_eq!(events.last(), Some(&Some(LinkEvent::Lost)));
+        assert_eq!(guest.state(), LinkState::Idle);
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        let mut link = Link::new();
+        link.connect(|| 1, 0);
+        let hello = frame(Message::Hello { version: VERSION + 1, nonce: 2 });
+        assert!(hello.iter().all(|byte| link.receive(*byte).is_none()));
+        assert_eq!(link.state(), LinkState::Hello);
+    }
+}
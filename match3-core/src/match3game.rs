@@ -0,0 +1,1671 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use pc_keyboard::{DecodedKey, KeyCode};
+use crate::ansi::SerialOutput;
+use crate::bitboard::{self, Bitboard, Bitboards, Line};
+use crate::date::{Date, DateTime};
+use crate::gem_generator::{GemGenerator, GemPolicy};
+use crate::input::{KeyboardLayout, RawKeyEvent};
+use crate::link::{Link, LinkEvent, Message};
+use crate::theme::Theme;
+
+pub const BOARD_HEIGHT: usize = 8;
+pub const BOARD_WIDTH: usize = 8;
+pub const GEM_COUNT: usize = 7;
+/// A block of garbage from the opponent in a versus game. It never matches and cannot be swapped,
+/// but falls like a gem, and a match next to it clears it.
+pub const STONE: u8 = GEM_COUNT as u8 + 1;
+const REFRESH_PERIOD: u64 = 4;
+/// Gem offsets from get_offset() are measured in 1/ANIMATION_STEPS of a cell. A falling gem
+/// moves one such step per tick, so it covers exactly one cell per drop_step.
+pub const ANIMATION_STEPS: i8 = REFRESH_PERIOD as i8;
+/// Ticks that matched gems flash before they are removed.
+pub const CLEAR_TICKS: u8 = 6;
+/// Ticks that a score popup stays on screen.
+pub const POPUP_TICKS: u8 = 12;
+const MAX_POPUPS: usize = 16;
+/// Gem policy for the daily challenge. It is fixed, so that everyone gets the same board.
+const DAILY_GEM_POLICY: GemPolicy = GemPolicy::Bag;
+/// Gem policy for versus games. Both sides must deal the same gems, whatever their settings.
+const VERSUS_GEM_POLICY: GemPolicy = GemPolicy::Bag;
+/// Ticks an arrow key must be held before the cursor starts repeating.
+const REPEAT_DELAY: u8 = 6;
+/// Ticks between cursor moves while an arrow key stays held.
+const REPEAT_INTERVAL: u8 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameState { EnteringCode, Connecting, Playing, Paused, Settings }
+
+/// Choices on the pause menu, in the order they are shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PauseOption { Resume, Restart, NewCode, Settings, Quit }
+
+impl PauseOption {
+    pub const ALL: [PauseOption; 5] = [
+        PauseOption::Resume, PauseOption::Restart, PauseOption::NewCode, PauseOption::Settings, PauseOption::Quit
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Restart => "Restart (same code)",
+            PauseOption::NewCode => "New code",
+            PauseOption::Settings => "Settings",
+            PauseOption::Quit => "Quit to menu",
+        }
+    }
+}
+
+/// Rows of the settings menu, in the order they are shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SettingsOption { Theme, Display, Serial, Keyboard, Gems, Back }
+
+impl SettingsOption {
+    pub const ALL: [SettingsOption; 6] = [
+        SettingsOption::Theme, SettingsOption::Display, SettingsOption::Serial, SettingsOption::Keyboard,
+        SettingsOption::Gems, SettingsOption::Back
+    ];
+}
+
+/// Player preferences that outlast a single game.
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub theme: Theme,
+    /// Draw the board in 320x200 graphics mode instead of text mode.
+    pub graphics: bool,
+    /// Whether the kernel also sends its frames to the serial console, or only there.
+    pub serial: SerialOutput,
+    pub layout: KeyboardLayout,
+    /// How gems are dealt in games started from a code. The daily challenge always uses
+    /// DAILY_GEM_POLICY.
+    pub gems: GemPolicy
+}
+
+/// The best score so far, and the day it was set if the clock was known.
+#[derive(Clone, Copy)]
+pub struct HighScore {
+    pub score: usize,
+    pub date: Option<Date>
+}
+
+/// How a versus game turned out, decided by whichever board runs out of moves first.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome { Won, Lost }
+
+/// A game against another kernel over the serial link, as far as its messages have told.
+#[derive(Clone, Copy, Default)]
+pub struct Versus {
+    pub opponent_score: usize,
+    pub opponent_moves: usize,
+    /// Whether the opponent's game has ended.
+    pub opponent_over: bool,
+    pub lines_sent: usize,
+    pub lines_received: usize,
+    pub outcome: Option<Outcome>,
+    /// Whether the opponent stopped answering, which forfeits the game like leaving it.
+    pub opponent_gone: bool,
+    /// The score the opponent was last told, so that it is only sent when it changes.
+    score_sent: usize,
+    /// Whether the opponent has been told that this game ended.
+    over_sent: bool
+}
+
+pub struct GameStateManager {
+    state: GameState,
+    game_code: GameCode,
+    game: Game,
+    seed: u64,
+    /// How gems are dealt in the current game.
+    policy: GemPolicy,
+    clock: Option<DateTime>,
+    high_score: Option<HighScore>,
+    /// The day whose challenge is being played, if the game was started with the daily option.
+    daily: Option<Date>,
+    /// The best daily challenge score, dated with the day of the challenge.
+    daily_best: Option<HighScore>,
+    /// Whether the current game has already been considered for the high score.
+    score_recorded: bool,
+    /// The most recent timer tick, which seeds random games.
+    last_tick: u64,
+    /// Supplies fresh randomness for random games, such as the kernel's hardware random number
+    /// generator.
+    entropy: fn() -> u64,
+    settings: Settings,
+    /// Whether the serial output has been chosen, by the player or by serial_console_seen().
+    serial_chosen: bool,
+    pause_selection: usize,
+    settings_selection: usize,
+    /// The serial link to another kernel, for versus games.
+    link: Link,
+    /// The versus game being played, if the current game is one.
+    versus: Option<Versus>
+}
+
+impl GameStateManager {
+    /// **entropy** is called for a new seed whenever a random game starts.
+    pub fn new(entropy: fn() -> u64) -> Self {
+        Self {
+            state: GameState::EnteringCode,
+            game_code: GameCode::new(),
+            game: Game::new(0, GemPolicy::Uniform),
+            seed: 0,
+            policy: GemPolicy::Uniform,
+            clock: None,
+            high_score: None,
+            daily: None,
+            daily_best: None,
+            score_recorded: false,
+            last_tick: 0,
+            entropy,
+            settings: Settings { theme: Theme::Classic, graphics: false, serial: SerialOutput::Off, layout: KeyboardLayout::Us104Key, gems: GemPolicy::Uniform },
+            serial_chosen: false,
+            pause_selection: 0,
+            settings_selection: 0,
+            link: Link::new(),
+            versus: None
+        }
+    }
+
+    pub fn key(&mut self, key: DecodedKey) {
+        const K_BACKSPACE: char = 0x08 as char;
+        const K_ESCAPE: char = 0x1b as char;
+        match self.state {
+            GameState::EnteringCode => {
+                match key {
+                    DecodedKey::Unicode('\n') => self.start_game(),
+                    DecodedKey::Unicode('\t') => self.settings.theme = self.settings.theme.next(),
+                    DecodedKey::RawKey(KeyCode::F2) => self.settings.graphics = !self.settings.graphics,
+                    DecodedKey::RawKey(KeyCode::F3) => self.start_daily_game(),
+                    DecodedKey::RawKey(KeyCode::F4) => self.next_serial_output(),
+                    DecodedKey::RawKey(KeyCode::F5) => self.find_opponent(),
+                    DecodedKey::Unicode(K_BACKSPACE) => self.game_code.backspace(),
+                    DecodedKey::Unicode(c @ ' '..='~') => self.game_code.type_char(c),
+                    _ => {}
+                }
+            },
+            GameState::Connecting => {
+                if key == DecodedKey::Unicode(K_ESCAPE) {
+                    self.link.disconnect();
+                    self.state = GameState::EnteringCode;
+                }
+            },
+            GameState::Playing => {
+                match key {
+                    DecodedKey::Unicode(K_ESCAPE) => {
+                        self.pause_selection = 0;
+                        self.state = GameState::Paused;
+                    },
+                    key => self.game.key(key)
+                }
+            },
+            GameState::Paused => {
+                match key {
+                    DecodedKey::Unicode(K_ESCAPE) => self.state = GameState::Playing,
+                    key => match menu_key(key) {
+                        Some(MenuKey::Move(dir)) => menu_move(&mut self.pause_selection, PauseOption::ALL.len(), dir),
+                        Some(MenuKey::Choose) => self.choose_pause_option(PauseOption::ALL[self.pause_selection]),
+                        None => {}
+                    }
+                }
+            },
+            GameState::Settings => {
+                let option = SettingsOption::ALL[self.settings_selection];
+                match key {
+                    DecodedKey::Unicode(K_ESCAPE) => self.state = GameState::Paused,
+                    key => match menu_key(key) {
+                        Some(MenuKey::Move(dir @ (Direction::Up | Direction::Down))) =>
+                            menu_move(&mut self.settings_selection, SettingsOption::ALL.len(), dir),
+                        Some(MenuKey::Move(_)) | Some(MenuKey::Choose) => self.change_setting(option),
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles keys going down and up, which the game uses to auto-repeat cursor movement.
+    pub fn raw_key(&mut self, event: RawKeyEvent) {
+        // a key released on a menu still has to stop repeating
+        if self.state == GameState::Playing || event.is_release() {
+            self.game.raw_key(event);
+        }
+    }
+
+    pub fn tick(&mut self, current_tick: u64) {
+        self.last_tick = current_tick;
+        if self.link.tick() == Some(LinkEvent::Lost) {
+            self.lose_opponent();
+        }
+        match self.state {
+            GameState::EnteringCode | GameState::Connecting | GameState::Paused | GameState::Settings => {},
+            GameState::Playing => {
+                self.game.tick(current_tick);
+                if !self.game.is_alive() {
+                    self.record_score();
+                }
+                self.report_to_opponent();
+            },
+        }
+    }
+
+    /// Call when the first byte arrives from the serial console. Someone is evidently at the other
+    /// end, so frames start being mirrored to it, unless the serial output was already chosen.
+    pub fn serial_console_seen(&mut self) {
+        if !self.serial_chosen {
+            self.serial_chosen = true;
+            if self.settings.serial == SerialOutput::Off {
+                self.settings.serial = SerialOutput::Mirror;
+            }
+        }
+    }
+
+    fn next_serial_output(&mut self) {
+        self.settings.serial = self.settings.serial.next();
+        self.serial_chosen = true;
+    }
+
+    /// Handles a byte from the serial link to another kernel.
+    pub fn link_byte(&mut self, byte: u8) {
+        match self.link.receive(byte) {
+            Some(LinkEvent::Start(seed)) if self.state == GameState::Connecting => self.start_versus_game(seed),
+            Some(LinkEvent::Message(message)) => self.hear_from_opponent(message),
+            _ => {}
+        }
+    }
+
+    /// Returns the bytes waiting to be sent on the serial link, and forgets them.
+    pub fn take_link_output(&mut self) -> Vec<u8> {
+        self.link.take_output()
+    }
+
+    /// Updates the wall-clock time, which is used to date high scores.
+    pub fn set_clock(&mut self, now: DateTime) {
+        self.clock = Some(now);
+    }
+
+    /// Counts the current game toward the high score, once it is over or abandoned.
+    fn record_score(&mut self) {
+        if self.score_recorded { return; }
+        self.score_recorded = true;
+        let score = self.game.get_score() * 100;
+        if score > 0 && self.high_score.is_none_or(|best| score > best.score) {
+            self.high_score = Some(HighScore { score, date: self.clock.map(|now| now.date) });
+        }
+        if let Some(day) = self.daily {
+            let beaten = match self.daily_best {
+                Some(best) => best.date != Some(day) || score > best.score,
+                None => true
+            };
+            if score > 0 && beaten {
+                self.daily_best = Some(HighScore { score, date: Some(day) });
+            }
+        }
+    }
+
+    /// Starts a game from the typed code. An empty code starts a random game, and its seed
+    /// code is filled in so that the game can be shared.
+    fn start_game(&mut self) {
+        self.record_score();
+        self.policy = self.settings.gems;
+        if self.game_code.code.is_empty() {
+            self.seed = random_seed(self.last_tick, self.entropy);
+            self.game_code.code = seed_code(self.seed, self.policy);
+        } else {
+            (self.seed, self.policy) = code_seed(&self.game_code.code, self.policy);
+        }
+        self.daily = None;
+        self.restart_game();
+    }
+
+    /// Starts today's challenge, which has the same board for everyone who plays it that day.
+    /// Does nothing if the date is unknown.
+    fn start_daily_game(&mut self) {
+        if let Some(now) = self.clock {
+            self.record_score();
+            self.seed = daily_seed(now.date);
+            self.policy = DAILY_GEM_POLICY;
+            self.daily = Some(now.date);
+            self.restart_game();
+        }
+    }
+
+    /// Starts looking for another kernel on the serial link to play against.
+    fn find_opponent(&mut self) {
+        self.link.connect(self.entropy, random_seed(self.last_tick, self.entropy));
+        self.state = GameState::Connecting;
+    }
+
+    /// Starts the versus game that both kernels agreed on.
+    fn start_versus_game(&mut self, seed: u64) {
+        self.record_score();
+        self.seed = seed;
+        self.policy = VERSUS_GEM_POLICY;
+        self.game_code.code = seed_code(seed, self.policy);
+        self.daily = None;
+        self.versus = Some(Versus::default());
+        self.restart_game();
+    }
+
+    /// Tells the opponent what happened on this board since the last tick.
+    fn report_to_opponent(&mut self) {
+        let Some(versus) = &mut self.versus else { return };
+        if let Some(mv) = self.game.take_played() {
+            self.link.send(Message::Swap(mv));
+        }
+        let lines = self.game.take_garbage();
+        if lines > 0 {
+            versus.lines_sent += lines;
+            self.link.send(Message::Garbage(lines.min(u8::MAX as usize) as u8));
+        }
+        let score = self.game.get_score();
+        if score != versus.score_sent {
+            versus.score_sent = score;
+            self.link.send(Message::Score(score as u32));
+        }
+        if !self.game.is_alive() && !versus.over_sent {
+            versus.over_sent = true;
+            versus.outcome.get_or_insert(Outcome::Lost);
+            self.link.send(Message::GameOver(score as u32));
+        }
+    }
+
+    fn hear_from_opponent(&mut self, message: Message) {
+        let Some(versus) = &mut self.versus else { return };
+        match message {
+            Message::Swap(_) => versus.opponent_moves += 1,
+            Message::Score(score) => versus.opponent_score = score as usize,
+            Message::Garbage(lines) => {
+                versus.lines_received += lines as usize;
+                self.game.add_garbage(lines as usize);
+            },
+            Message::GameOver(score) => {
+                versus.opponent_score = score as usize;
+                versus.opponent_over = true;
+                versus.outcome.get_or_insert(Outcome::Won);
+            },
+            Message::Hello { .. } | Message::Seed(_) | Message::Ack(_) => {}
+        }
+    }
+
+    /// The opponent has gone silent, so its game is over, and forfeited if it was not already.
+    fn lose_opponent(&mut self) {
+        let Some(versus) = &mut self.versus else { return };
+        versus.opponent_gone = true;
+        versus.opponent_over = true;
+        versus.outcome.get_or_insert(Outcome::Won);
+    }
+
+    /// Ends the versus game, if one is being played. Leaving before the board runs out of moves
+    /// forfeits it.
+    fn leave_versus(&mut self) {
+        if let Some(versus) = self.versus.take() {
+            if !versus.over_sent {
+                self.link.send(Message::GameOver(self.game.get_score() as u32));
+            }
+            self.link.disconnect();
+        }
+    }
+
+    fn restart_game(&mut self) {
+        self.record_score();
+        self.game = Game::new(self.seed, self.policy);
+        self.score_recorded = false;
+        self.state = GameState::Playing;
+    }
+
+    fn return_to_code_menu(&mut self) {
+        self.record_score();
+        self.state = GameState::EnteringCode;
+    }
+
+    fn choose_pause_option(&mut self, option: PauseOption) {
+        if matches!(option, PauseOption::Restart | PauseOption::NewCode | PauseOption::Quit) {
+            self.leave_versus();
+        }
+        match option {
+            PauseOption::Resume => self.state = GameState::Playing,
+            PauseOption::Restart => self.restart_game(),
+            PauseOption::NewCode => {
+                self.game_code = GameCode::new();
+                self.return_to_code_menu();
+            },
+            PauseOption::Settings => {
+                self.settings_selection = 0;
+                self.state = GameState::Settings;
+            },
+            PauseOption::Quit => self.return_to_code_menu(),
+        }
+    }
+
+    fn change_setting(&mut self, option: SettingsOption) {
+        match option {
+            SettingsOption::Theme => self.settings.theme = self.settings.theme.next(),
+            SettingsOption::Display => self.settings.graphics = !self.settings.graphics,
+            SettingsOption::Serial => self.next_serial_output(),
+            SettingsOption::Keyboard => self.settings.layout = next_layout(self.settings.layout),
+            SettingsOption::Gems => self.settings.gems = self.settings.gems.next(),
+            SettingsOption::Back => self.state = GameState::Paused,
+        }
+    }
+
+    pub fn get_state(&self) -> GameState { self.state }
+    pub fn get_game(&self) -> &Game { &self.game }
+    pub fn get_code(&self) -> &str { &self.game_code.code }
+    pub fn get_seed(&self) -> u64 { self.seed }
+
+    /// Returns the code that reproduces the current game, in the **#** hex form.
+    pub fn get_seed_code(&self) -> String { seed_code(self.seed, self.policy) }
+
+    /// Returns the code the current game was started from, unless it was a daily challenge or
+    /// the code already is the seed code.
+    pub fn get_typed_code(&self) -> Option<&str> {
+        let code = self.get_code();
+        if self.daily.is_some() || parse_seed_code(code).is_some() { None } else { Some(code) }
+    }
+    pub fn get_clock(&self) -> Option<DateTime> { self.clock }
+    pub fn get_high_score(&self) -> Option<HighScore> { self.high_score }
+    pub fn get_daily(&self) -> Option<Date> { self.daily }
+
+    /// Returns the best score for today's challenge, if it has been played.
+    pub fn get_daily_best(&self) -> Option<HighScore> {
+        let today = self.clock.map(|now| now.date);
+        self.daily_best.filter(|best| today.is_some() && best.date == today)
+    }
+    pub fn get_settings(&self) -> Settings { self.settings }
+    pub fn get_link(&self) -> &Link { &self.link }
+    pub fn get_versus(&self) -> Option<&Versus> { self.versus.as_ref() }
+    pub fn get_pause_selection(&self) -> PauseOption { PauseOption::ALL[self.pause_selection] }
+    pub fn get_settings_selection(&self) -> SettingsOption { SettingsOption::ALL[self.settings_selection] }
+}
+
+enum MenuKey { Move(Direction), Choose }
+
+/// Interprets a key press on a menu screen.
+fn menu_key(key: DecodedKey) -> Option<MenuKey> {
+    use DecodedKey::*;
+    match key {
+        RawKey(KeyCode::ArrowUp)    | Unicode('w') => Some(MenuKey::Move(Direction::Up)),
+        RawKey(KeyCode::ArrowDown)  | Unicode('s') => Some(MenuKey::Move(Direction::Down)),
+        RawKey(KeyCode::ArrowLeft)  | Unicode('a') => Some(MenuKey::Move(Direction::Left)),
+        RawKey(KeyCode::ArrowRight) | Unicode('d') => Some(MenuKey::Move(Direction::Right)),
+        Unicode('\n') | Unicode(' ') => Some(MenuKey::Choose),
+        _ => None
+    }
+}
+
+/// Moves a menu selection up or down, wrapping around at either end.
+fn menu_move(selection: &mut usize, len: usize, dir: Direction) {
+    match dir {
+        Direction::Up => *selection = (*selection + len - 1) % len,
+        Direction::Down => *selection = (*selection + 1) % len,
+        Direction::Left | Direction::Right => {}
+    }
+}
+
+/// Marks a code that spells out a seed in hexadecimal, rather than text to be hashed.
+pub const SEED_CODE_PREFIX: char = '#';
+
+/// Separates the gem policy letter from the seed in a seed code.
+const POLICY_SEPARATOR: char = '-';
+
+/// Writes **seed** and **policy** as a code that reproduces the game exactly. The policy is
+/// left out when it is Uniform.
+pub fn seed_code(seed: u64, policy: GemPolicy) -> String {
+    match policy {
+        GemPolicy::Uniform => format!("{}{:016x}", SEED_CODE_PREFIX, seed),
+        policy => format!("{}{:016x}{}{}", SEED_CODE_PREFIX, seed, POLICY_SEPARATOR, policy.letter()),
+    }
+}
+
+/// Reads a code written by seed_code(). Leading zeros may be left out.
+fn parse_seed_code(code: &str) -> Option<(u64, GemPolicy)> {
+    let code = code.strip_prefix(SEED_CODE_PREFIX)?;
+    let (hex, policy) = match code.split_once(POLICY_SEPARATOR) {
+        Some((hex, letter)) => {
+            let mut letters = letter.chars();
+            match (letters.next(), letters.next()) {
+                (Some(letter), None) => (hex, GemPolicy::from_letter(letter)?),
+                _ => return None
+            }
+        },
+        None => (code, GemPolicy::Uniform)
+    };
+    if hex.is_empty() || hex.len() > 16 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((u64::from_str_radix(hex, 16).ok()?, policy))
+}
+
+/// Returns the seed and gem policy that the typed **code** starts. A seed code names both;
+/// any other code is hashed into a seed and dealt with **policy**.
+pub fn code_seed(code: &str, policy: GemPolicy) -> (u64, GemPolicy) {
+    parse_seed_code(code).unwrap_or_else(|| (hash_code(code), policy))
+}
+
+/// Picks a seed for a random game from the frontend's **entropy** source, mixed with the timer
+/// tick.
+fn random_seed(tick: u64, entropy: fn() -> u64) -> u64 {
+    mix(tick ^ entropy())
+}
+
+/// Derives the seed for a day's challenge. It only depends on the date, so every copy of the
+/// game deals the same board that day.
+fn daily_seed(date: Date) -> u64 {
+    mix(date.year as u64 * 10000 + date.month as u64 * 100 + date.day as u64)
+}
+
+/// The splitmix64 finalizer, so that nearby inputs, such as consecutive days or ticks, give
+/// unrelated seeds.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E3779B97F4A7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+    x ^ (x >> 31)
+}
+
+fn next_layout(layout: KeyboardLayout) -> KeyboardLayout {
+    let all = KeyboardLayout::ALL;
+    let i = all.iter().position(|l| *l == layout).unwrap_or(0);
+    all[(i + 1) % all.len()]
+}
+
+/// Longest code that can be typed; it has to fit on one row of the screen.
+pub const MAX_CODE_LEN: usize = 80;
+
+struct GameCode {
+    code: String
+}
+
+impl GameCode {
+    fn new() -> Self {
+        Self { code: String::new() }
+    }
+
+    fn type_char(&mut self, c: char) {
+        if self.code.len() < MAX_CODE_LEN {
+            self.code.push(c);
+        }
+    }
+
+    fn backspace(&mut self) {
+        self.code.pop();
+    }
+}
+
+/// Turns a typed code into a seed.
+fn hash_code(code: &str) -> u64 {
+    let mut x = 5040;
+    for c in code.chars() {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x ^= c as u64;
+    }
+    x
+}
+
+#[derive(Clone)]
+pub struct Game {
+    board: [[u8; BOARD_HEIGHT]; BOARD_WIDTH],
+    falling: [[u8; BOARD_HEIGHT]; BOARD_WIDTH],
+    swap: Option<SwapAnimation>,
+    clearing: Option<Clearing>,
+    popups: [Option<Popup>; MAX_POPUPS],
+    /// Deals each column from its own generator, so that refilling one column never changes
+    /// what falls into another. Two games with the same seed and policy see the same gems in
+    /// each column whatever is played.
+    gems: GemGenerator,
+    cursor: GameCursor,
+    held: Option<HeldKey>,
+    selected: bool,
+    alive: bool,
+    /// Whether the board has settled with no matches since the last swap, so that is_alive()
+    /// is up to date.
+    settled: bool,
+    score: usize,
+    /// Lines of garbage earned by long matches that have not been taken by take_garbage() yet.
+    garbage_out: usize,
+    /// Lines of garbage waiting to rise from the bottom of the board.
+    garbage_in: usize,
+    /// The last swap that was accepted, until take_played() takes it.
+    played: Option<Move>
+}
+
+impl Game {
+    /// A game dealt from **seed** with **policy**. The board starts empty and fills over the
+    /// first ticks.
+    pub fn new(seed: u64, policy: GemPolicy) -> Self {
+        Self{
+            board: [[0; BOARD_HEIGHT]; BOARD_WIDTH],
+            falling: [[0; BOARD_HEIGHT]; BOARD_WIDTH],
+            swap: None,
+            clearing: None,
+            popups: [None; MAX_POPUPS],
+            gems: GemGenerator::new(policy, seed),
+            cursor: GameCursor::new(),
+            held: None,
+            selected: false,
+            alive: true,
+            settled: false,
+            score: 0,
+            garbage_out: 0,
+            garbage_in: 0,
+            played: None
+        }
+    }
+
+    /// Find and score all existing matches, and start them flashing along with any stones next to
+    /// them. They are removed once the flashing is over.
+    fn score_matches(&mut self) {
+        let matched = self.calculate_marks();
+        let marks = matched | (bitboard::neighbors(matched) & self.stones());
+        if marks != 0 {
+            self.clearing = Some(Clearing { marks, ticks_left: CLEAR_TICKS });
+        }
+    }
+
+    /// Find and mark any matches on the board, and update score based on those matches
+    fn calculate_marks(&mut self) -> Bitboard {
+        let boards = Bitboards::from_board(&self.board);
+        for run in boards.runs(Line::Vertical) {
+            self.score += Self::calculate_score(run.len);
+            self.garbage_out += Self::calculate_garbage(run.len);
+            self.add_popup(Popup {
+                center: (run.col * 2 + 1, run.row * 2 + run.len),
+                points: Self::calculate_score(run.len),
+                age: 0
+            });
+        }
+        for run in boards.runs(Line::Horizontal) {
+            self.score += Self::calculate_score(run.len);
+            self.garbage_out += Self::calculate_garbage(run.len);
+            self.add_popup(Popup {
+                center: (run.col * 2 + run.len, run.row * 2 + 1),
+                points: Self::calculate_score(run.len),
+                age: 0
+            });
+        }
+        boards.matches()
+    }
+
+    /// Scoring calculator:
+    /// match-3 = 3 points
+    /// match-4 = 3 + 4 = 7 points
+    /// match-n = 3 + 4 + ... + n points
+    fn calculate_score(match_len: usize) -> usize {
+        let mut x = 0;
+        for i in 3..=match_len { x += i; }
+        x
+    }
+
+    /// Lines of garbage sent to the opponent by a match:
+    /// match-3 = none
+    /// match-4 = 1 line
+    /// match-5 or longer = 2 lines
+    fn calculate_garbage(match_len: usize) -> usize {
+        match_len.saturating_sub(3).min(2)
+    }
+
+    /// Returns the cells that hold stones.
+    fn stones(&self) -> Bitboard {
+        let mut stones = 0;
+        for (col, column) in self.board.iter().enumerate() {
+            for (row, gem) in column.iter().enumerate() {
+                if *gem == STONE {
+                    stones |= bitboard::cell(col, row);
+                }
+            }
+        }
+        stones
+    }
+
+    /// Pushes every column up a row, losing the top row, and fills the bottom row with stones.
+    /// Since the whole board moves together, this never makes a match.
+    fn rise(&mut self) {
+        for col in 0..BOARD_WIDTH {
+            self.board[col].copy_within(1.., 0);
+            self.falling[col].copy_within(1.., 0);
+            self.board[col][BOARD_HEIGHT - 1] = STONE;
+            self.falling[col][BOARD_HEIGHT - 1] = 0;
+        }
+    }
+
+    /// Stores a new popup, replacing the oldest one if there is no room.
+    fn add_popup(&mut self, popup: Popup) {
+        let slot = match self.popups.iter().position(|p| p.is_none()) {
+            Some(i) => i,
+            None => (0..MAX_POPUPS).max_by_key(|i| self.popups[*i].map_or(0, |p| p.age)).unwrap_or(0)
+        };
+        self.popups[slot] = Some(popup);
+    }
+
+    /// Erase all marked gems, then reset the markings
+    fn remove_marked(&mut self, marks: Bitboard) {
+        for (col, column) in self.board.iter_mut().enumerate() {
+            for (row, gem) in column.iter_mut().enumerate() {
+                if marks & bitboard::cell(col, row) != 0 {
+                    *gem = 0;
+                }
+            }
+        }
+    }
+
+    /// Move all suspended gems down one space; returns whether any gems were moved.
+    fn drop_step(&mut self) -> bool {
+        let mut ongoing = false;
+        for col in 0..BOARD_WIDTH {
+            let mut row = BOARD_HEIGHT - 2;
+            loop {
+                let current = self.board[col][row];
+                if current != 0 && self.board[col][row+1] == 0 {
+                    self.board[col][row+1] = current;
+                    self.board[col][row] = 0;
+                    self.falling[col][row+1] = ANIMATION_STEPS as u8 - 1;
+                    self.falling[col][row] = 0;
+                    ongoing = true;
+                } else if row == 0 {
+                    break;
+                } else {
+                    row -= 1;
+                }
+            }
+        }
+        ongoing
+    }
+
+    /// Drop at most one gem into the top of all available columns; returns whether any gems were dropped.
+    fn fill_step(&mut self) -> bool {
+        let mut any: bool = false;
+        for col in 0..BOARD_WIDTH {
+            if self.board[col][0] == 0 {
+                self.board[col][0] = self.gems.next(col, &self.board);
+                self.falling[col][0] = ANIMATION_STEPS as u8 - 1;
+                any = true;
+            }
+        }
+        any
+    }
+
+    /// Swaps the gem at (c,r) with the gem at the cursor's location.
+    fn swap_cursor_raw(&mut self, c: usize, r: usize) {
+        let temp = self.board[self.cursor.0][self.cursor.1];
+        self.board[self.cursor.0][self.cursor.1] = self.board[c][r];
+        self.board[c][r] = temp;
+    }
+
+    /// Swaps the piece under the cursor and the piece in the `dir` direction from the cursor.
+    fn swap_cursor(&mut self, dir: Direction) {
+        match dir {
+            Direction::Up => {
+                if self.cursor.1 > 0 {
+                    self.swap_cursor_raw(self.cursor.0, self.cursor.1 - 1);
+                }
+            },
+            Direction::Down => {
+                if self.cursor.1 < BOARD_HEIGHT - 1 {
+                    self.swap_cursor_raw(self.cursor.0, self.cursor.1 + 1);
+                }
+            },
+            Direction::Left => {
+                if self.cursor.0 > 0 {
+                    self.swap_cursor_raw(self.cursor.0 - 1, self.cursor.1);
+                }
+            },
+            Direction::Right => {
+                if self.cursor.0 < BOARD_WIDTH - 1 {
+                    self.swap_cursor_raw(self.cursor.0 + 1, self.cursor.1);
+                }
+            }
+        }
+    }
+
+    /// Returns the location next to the cursor in the `dir` direction, if it is on the board.
+    fn cursor_neighbor(&self, dir: Direction) -> Option<(usize, usize)> {
+        match dir {
+            Direction::Up    if self.cursor.1 > 0                 => Some((self.cursor.0, self.cursor.1 - 1)),
+            Direction::Down  if self.cursor.1 < BOARD_HEIGHT - 1  => Some((self.cursor.0, self.cursor.1 + 1)),
+            Direction::Left  if self.cursor.0 > 0                 => Some((self.cursor.0 - 1, self.cursor.1)),
+            Direction::Right if self.cursor.0 < BOARD_WIDTH - 1   => Some((self.cursor.0 + 1, self.cursor.1)),
+            _ => None,
+        }
+    }
+
+    /// Check if the swap that was just performed in the given direction makes any match.
+    fn makes_match(&self, dir: Direction) -> bool {
+        let other_pos = self.cursor_neighbor(dir).unwrap_or(self.cursor.location());
+        if self.board[self.cursor.0][self.cursor.1] == STONE || self.board[other_pos.0][other_pos.1] == STONE {
+            return false;
+        }
+        let swapped = bitboard::cell(self.cursor.0, self.cursor.1) | bitboard::cell(other_pos.0, other_pos.1);
+        Bitboards::from_board(&self.board).matches() & swapped != 0
+    }
+
+    /// Check if there are any valid moves left
+    fn check_for_game_over(&mut self) {
+        self.alive = Bitboards::from_board(&self.board).has_legal_move();
+    }
+
+    pub fn is_selected(&self) -> bool {
+        self.selected
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    /// Returns whether the board has settled with no matches left to clear, so that the game is
+    /// either over or waiting for a move.
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Returns every swap that would make a match.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for col in 0..BOARD_WIDTH {
+            for row in 0..BOARD_HEIGHT {
+                for dir in [Direction::Right, Direction::Down] {
+                    let mv = Move { col, row, dir };
+                    if mv.other().is_some() && self.move_points(mv) > 0 {
+                        moves.push(mv);
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    /// Returns the points that the matches made by **mv** would score, not counting any matches
+    /// that follow once gems fall. A move that makes no match scores nothing.
+    pub fn move_points(&self, mv: Move) -> usize {
+        let Some((c, r)) = mv.other() else { return 0 };
+        let mut board = self.board;
+        if [board[mv.col][mv.row], board[c][r]].iter().any(|gem| *gem == 0 || *gem == STONE) { return 0; }
+        let temp = board[mv.col][mv.row];
+        board[mv.col][mv.row] = board[c][r];
+        board[c][r] = temp;
+        let boards = Bitboards::from_board(&board);
+        let swapped = bitboard::cell(mv.col, mv.row) | bitboard::cell(c, r);
+        if boards.matches() & swapped == 0 { return 0; }
+        boards.runs(Line::Vertical).chain(boards.runs(Line::Horizontal))
+            .map(|run| Self::calculate_score(run.len))
+            .sum()
+    }
+
+    /// Makes **mv** as a player would, by selecting the gem and moving toward its neighbor.
+    /// Returns whether the swap was accepted.
+    pub fn play(&mut self, mv: Move) -> bool {
+        if self.swap.is_some() || self.clearing.is_some() || mv.other().is_none() { return false; }
+        self.cursor.set_cursor(mv.col, mv.row);
+        self.selected = true;
+        self.do_action(InputAction::Move(mv.dir));
+        self.selected = false;
+        self.swap.is_some_and(|swap| swap.accepted)
+    }
+
+    /// Adds **lines** of garbage, which rise from the bottom of the board one at a time whenever it
+    /// settles.
+    pub fn add_garbage(&mut self, lines: usize) {
+        self.garbage_in += lines;
+    }
+
+    /// Returns the lines of garbage that long matches have earned since the last call.
+    pub fn take_garbage(&mut self) -> usize {
+        core::mem::take(&mut self.garbage_out)
+    }
+
+    /// Returns the lines of garbage that have not risen yet.
+    pub fn get_pending_garbage(&self) -> usize {
+        self.garbage_in
+    }
+
+    /// Returns the swap accepted since the last call, if there was one.
+    pub fn take_played(&mut self) -> Option<Move> {
+        self.played.take()
+    }
+
+    pub fn get_board(&self) -> [[u8; BOARD_HEIGHT]; BOARD_WIDTH] {
+        self.board
+    }
+
+    pub fn get_cursor(&self) -> &GameCursor {
+        &self.cursor
+    }
+
+    pub fn get_score(&self) -> usize {
+        self.score
+    }
+
+    /// Returns the number of ticks left before the gem at (col, row) is removed, if it is part of
+    /// a match that is flashing.
+    pub fn get_clearing(&self, col: usize, row: usize) -> Option<u8> {
+        match self.clearing {
+            Some(clearing) if clearing.marks & bitboard::cell(col, row) != 0 => Some(clearing.ticks_left),
+            _ => None
+        }
+    }
+
+    /// Returns the score popups that are currently visible.
+    pub fn get_popups(&self) -> impl Iterator<Item = &Popup> {
+        self.popups.iter().flatten()
+    }
+
+    /// Returns how far the gem at (col, row) should be drawn from its cell, as (columns, rows)
+    /// in units of 1/ANIMATION_STEPS of a cell. Negative values are up and to the left.
+    pub fn get_offset(&self, col: usize, row: usize) -> (i8, i8) {
+        let mut offset = (0, -(self.falling[col][row] as i8));
+        if let Some(swap) = self.swap {
+            let amount = swap.amount();
+            let (a, b) = (swap.origin, swap.other);
+            let toward = |from: (usize, usize), to: (usize, usize)| (
+                (to.0 as i8 - from.0 as i8) * amount,
+                (to.1 as i8 - from.1 as i8) * amount
+            );
+            if (col, row) == a {
+                offset = toward(a, b);
+            } else if (col, row) == b {
+                offset = toward(b, a);
+            }
+        }
+        offset
+    }
+
+    /// Advances gem animations by one tick.
+    fn animate(&mut self) {
+        for f in self.falling.iter_mut().flatten() {
+            *f = f.saturating_sub(1);
+        }
+        for slot in self.popups.iter_mut() {
+            if let Some(popup) = slot {
+                popup.age += 1;
+                if popup.age >= POPUP_TICKS {
+                    *slot = None;
+                }
+            }
+        }
+        if let Some(clearing) = &mut self.clearing {
+            clearing.ticks_left -= 1;
+            if clearing.ticks_left == 0 {
+                let marks = clearing.marks;
+                self.clearing = None;
+                self.remove_marked(marks);
+            }
+        }
+        if let Some(swap) = &mut self.swap {
+            swap.step += 1;
+            if swap.step >= ANIMATION_STEPS {
+                self.swap = None;
+            }
+        }
+    }
+
+    fn key(&mut self, key: DecodedKey) {
+        use DecodedKey::*;
+        let action = match key {
+            // the arrow keys are handled by raw_key(), so that they can auto-repeat
+            Unicode('w') => Some(InputAction::Move(Direction::Up)),
+            Unicode('s') => Some(InputAction::Move(Direction::Down)),
+            Unicode('a') => Some(InputAction::Move(Direction::Left)),
+            Unicode('d') => Some(InputAction::Move(Direction::Right)),
+            Unicode('\n') | Unicode(' ') => Some(InputAction::Select),
+            _ => None
+        };
+        if let Some(action) = action {
+            self.do_action(action);
+        }
+    }
+
+    /// Moves the cursor when an arrow key goes down, and remembers the key so that tick() can
+    /// repeat the move while it is held. The keyboard's own repeated presses are ignored.
+    fn raw_key(&mut self, event: RawKeyEvent) {
+        let dir = match event.code {
+            KeyCode::ArrowUp => Direction::Up,
+            KeyCode::ArrowDown => Direction::Down,
+            KeyCode::ArrowLeft => Direction::Left,
+            KeyCode::ArrowRight => Direction::Right,
+            _ => return
+        };
+        let is_held = self.held.is_some_and(|held| held.code == event.code);
+        if event.is_release() {
+            if is_held { self.held = None; }
+        } else if !is_held {
+            // a move that swaps gems should not repeat
+            let repeat = if self.selected { None } else { Some(dir) };
+            self.held = Some(HeldKey { code: event.code, repeat, ticks_left: REPEAT_DELAY });
+            self.do_action(InputAction::Move(dir));
+        }
+    }
+
+    fn repeat_held_key(&mut self) {
+        if let Some(held) = &mut self.held {
+            if let Some(dir) = held.repeat {
+                held.ticks_left -= 1;
+                if held.ticks_left == 0 {
+                    held.ticks_left = REPEAT_INTERVAL;
+                    if !self.selected {
+                        self.do_action(InputAction::Move(dir));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Advances the game by one timer tick.
+    pub fn tick(&mut self, current_tick: u64) {
+        self.repeat_held_key();
+        self.animate();
+        // let a swap finish sliding before anything it matched is cleared, and let cleared gems
+        // finish flashing before anything falls into their place
+        if self.swap.is_some() || self.clearing.is_some() { return; }
+        if current_tick.is_multiple_of(REFRESH_PERIOD) {
+            let drop = self.drop_step();
+            let fill = self.fill_step();
+            let settled = !drop && !fill;
+            if settled {
+                // only check for game over if board is settled and has no matches
+                let old_score = self.get_score();
+                self.score_matches();
+                if self.get_score() == old_score {
+                    if self.garbage_in > 0 && self.alive {
+                        // rise one line per refresh, and check the board again after the last
+                        self.garbage_in -= 1;
+                        self.rise();
+                        self.settled = false;
+                    } else {
+                        self.check_for_game_over();
+                        self.settled = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles actions performed on the game.
+    fn do_action(&mut self, action: InputAction) {
+        // the board cannot change until the swap has slid and any matched gems have flashed away
+        if self.swap.is_some() || self.clearing.is_some() { return; }
+        match action {
+            InputAction::Select => self.selected = !self.selected,
+            InputAction::Move(dir)  => {
+                if self.selected {
+                    if let Some(other) = self.cursor_neighbor(dir) {
+                        let origin = self.cursor.location();
+                        self.swap_cursor(dir);
+                        let accepted = self.makes_match(dir);
+                        if !accepted {
+                            self.swap_cursor(dir)
+                        } else {
+                            let (col, row) = origin;
+                            self.played = Some(Move { col, row, dir });
+                            self.cursor.move_cursor(dir);
+                            self.selected = false;
+                            self.settled = false;
+                        }
+                        self.swap = Some(SwapAnimation { origin, other, step: 0, accepted });
+                    }
+                } else {
+                    self.cursor.move_cursor(dir);
+                }
+            }
+        }
+    }
+}
+
+/// The loop versions of the match and move checks, which the bitboard versions replaced. They
+/// are kept as an oracle for testing the bitboard versions, so they are left as they were written.
+#[cfg(test)]
+#[allow(clippy::needless_range_loop, clippy::if_same_then_else)]
+impl Game {
+    /// Find and mark any matches on the board, and update score based on those matches
+    fn loop_calculate_marks(&mut self) -> [[u8; BOARD_HEIGHT]; BOARD_WIDTH] {
+        let mut points: usize = 0;
+        let mut marks = [[0u8; BOARD_HEIGHT]; BOARD_WIDTH];
+
+        // find vertical matches
+        let direction = 1u8;
+        for col in 0..BOARD_WIDTH {
+            for row in 0..BOARD_HEIGHT-2 {
+                let current = self.board[col][row];
+                if current == 0 || marks[col][row] & direction != 0 { continue; }
+                if self.board[col][row+1] == current && self.board[col][row+2] == current {
+                    marks[col][row]   |= direction;
+                    marks[col][row+1] |= direction;
+                    marks[col][row+2] |= direction;
+                    let mut size = 3;
+                    while row + size < BOARD_HEIGHT && self.board[col][row+size] == current {
+                        marks[col][row+size] |= direction;
+                        size += 1;
+                    }
+                    points += Self::calculate_score(size);
+                    self.add_popup(Popup {
+                        center: (col * 2 + 1, row * 2 + size),
+                        points: Self::calculate_score(size),
+                        age: 0
+                    });
+                }
+            }
+        }
+
+        // find horizontal matches
+        let direction = 2u8;
+        for col in 0..BOARD_WIDTH-2 {
+            for row in 0..BOARD_HEIGHT {
+                let current = self.board[col][row];
+                if current == 0 || marks[col][row] & direction != 0 { continue; }
+                if self.board[col+1][row] == current && self.board[col+2][row] == current {
+                    marks[col]  [row] |= direction;
+                    marks[col+1][row] |= direction;
+                    marks[col+2][row] |= direction;
+                    let mut size = 3;
+                    while col + size < BOARD_WIDTH && self.board[col+size][row] == current {
+                        marks[col+size][row] |= direction;
+                        size += 1;
+                    }
+                    points += Self::calculate_score(size);
+                    self.add_popup(Popup {
+                        center: (col * 2 + size, row * 2 + 1),
+                        points: Self::calculate_score(size),
+                        age: 0
+                    });
+                }
+            }
+        }
+        self.score += points;
+        marks
+    }
+
+    /// Check if the swap that was just performed in the given direction makes any match.
+    fn loop_makes_match(&self, dir: Direction) -> bool {
+        let other_pos = self.cursor_neighbor(dir).unwrap_or(self.cursor.location());
+        self.check_for_match(self.cursor.0, self.cursor.1) || self.check_for_match(other_pos.0, other_pos.1)
+    }
+
+    /// Check if the piece at (c,r) is part of a match.
+    fn check_for_match(&self, c: usize, r: usize) -> bool {
+        if c >= BOARD_WIDTH || r >= BOARD_HEIGHT { return false }
+        let current = self.board[c][r];
+        // vertical check
+        if r >= 2 && self.board[c][r-2] == current && self.board[c][r-1] == current {
+            return true
+        } else if r >= 1 && r + 1 < BOARD_HEIGHT && self.board[c][r-1] == current && self.board[c][r+1] == current {
+            return true
+        } else if r + 2 < BOARD_HEIGHT && self.board[c][r+1] == current && self.board[c][r+2] == current {
+            return true
+        }
+        // horizontal check
+        if c >= 2 && self.board[c-2][r] == current && self.board[c-1][r] == current {
+            return true
+        } else if c >= 1 && c + 1 < BOARD_WIDTH && self.board[c-1][r] == current && self.board[c+1][r] == current {
+            return true
+        } else if c + 2 < BOARD_WIDTH && self.board[c+1][r] == current && self.board[c+2][r] == current {
+            return true
+        }
+        false
+    }
+
+    /// Check if there are any valid moves left
+    fn loop_check_for_game_over(&mut self) {
+        self.alive = false;
+        let loc = self.cursor.location();
+        // search for vertical moves
+        for col in 0..BOARD_WIDTH {
+            for row in 0..BOARD_HEIGHT-1 {
+                self.cursor.set_cursor(col, row);
+                self.swap_cursor(Direction::Down);
+                if self.loop_makes_match(Direction::Down) {
+                    self.alive = true;
+                }
+                self.swap_cursor(Direction::Down);
+                if self.alive {
+                    self.cursor.set_cursor(loc.0, loc.1);
+                    return
+                }
+            }
+        }
+        // search for horizontal matches
+        self.cursor = GameCursor::new();
+        for row in 0..BOARD_HEIGHT {
+            for col in 0..BOARD_WIDTH-1 {
+                self.cursor.set_cursor(col, row);
+                self.swap_cursor(Direction::Right);
+                if self.loop_makes_match(Direction::Right) {
+                    self.alive = true;
+                }
+                self.swap_cursor(Direction::Right);
+                if self.alive {
+                    self.cursor.set_cursor(loc.0, loc.1);
+                    return
+                }
+            }
+        }
+        self.cursor.set_cursor(loc.0, loc.1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction { Up, Down, Left, Right }
+
+/// A swap of the gem at (col, row) with its neighbor in direction dir.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub col: usize,
+    pub row: usize,
+    pub dir: Direction
+}
+
+impl Move {
+    /// Returns the location of the gem this move swaps with, if it is on the board.
+    pub fn other(&self) -> Option<(usize, usize)> {
+        let mut cursor = GameCursor(self.col, self.row);
+        cursor.move_cursor(self.dir);
+        Some(cursor.location()).filter(|other| *other != (self.col, self.row))
+    }
+}
+
+/// Matched gems waiting to be removed.
+#[derive(Clone, Copy)]
+struct Clearing {
+    marks: Bitboard,
+    ticks_left: u8
+}
+
+/// Points scored by one match, shown rising from the middle of the match.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Popup {
+    /// Center of the match, in half cells: (1, 1) is the center of the top left cell.
+    pub center: (usize, usize),
+    pub points: usize,
+    /// Ticks since the match was scored, up to POPUP_TICKS.
+    pub age: u8
+}
+
+/// An arrow key that is being held down.
+#[derive(Clone, Copy)]
+struct HeldKey {
+    code: KeyCode,
+    /// Direction to keep moving the cursor, if this key repeats.
+    repeat: Option<Direction>,
+    ticks_left: u8
+}
+
+/// A swap between the gems that were at `origin` and `other` when it started. An accepted swap
+/// has already been made on the board, and the gems slide in from their old cells. A rejected
+/// swap slides the gems halfway toward each other and back.
+#[derive(Clone, Copy)]
+struct SwapAnimation {
+    origin: (usize, usize),
+    other: (usize, usize),
+    step: i8,
+    accepted: bool
+}
+
+impl SwapAnimation {
+    /// How far, in 1/ANIMATION_STEPS of a cell, the gem at `origin` is drawn toward `other`.
+    fn amount(&self) -> i8 {
+        if self.accepted {
+            ANIMATION_STEPS - self.step
+        } else {
+            self.step.min(ANIMATION_STEPS - self.step)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputAction { Move(Direction), Select }
+
+#[derive(Clone, Copy)]
+pub struct GameCursor(usize, usize);
+
+impl GameCursor {
+    fn new() -> Self {
+        Self(0, 0)
+    }
+    fn move_cursor(&mut self, dir: Direction) {
+        match dir {
+            Direction::Up    => if self.1 > 0                { self.1 -= 1; },
+            Direction::Down  => if self.1 < BOARD_HEIGHT - 1 { self.1 += 1; },
+            Direction::Left  => if self.0 > 0                { self.0 -= 1; },
+            Direction::Right => if self.0 < BOARD_WIDTH - 1  { self.0 += 1; }
+        }
+    }
+    fn set_cursor(&mut self, c: usize, r: usize) {
+        self.0 = c;
+        self.1 = r;
+    }
+    pub fn location(&self) -> (usize, usize) {
+        (self.0, self.1)
+    }
+}
+
+#[cfg(any(test, fuzzing))]
+pub mod invariants;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Rng, SeedableRng, Xoshiro256StarStar};
+
+    type Board = [[u8; BOARD_HEIGHT]; BOARD_WIDTH];
+
+    const BOARDS: u64 = 500;
+    const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    fn game_with(board: Board) -> Game {
+        let mut game = Game::new(0, GemPolicy::Uniform);
+        game.board = board;
+        game
+    }
+
+    /// Full boards of random gems. Some use only a few colors, so that they have plenty of
+    /// matches.
+    fn random_boards() -> impl Iterator<Item = Board> {
+        let mut rng = Xoshiro256StarStar::from_seed(320);
+        (0..BOARDS).map(move |i| {
+            let colors = 3 + i % (GEM_COUNT as u64 - 2);
+            let mut board = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+            for gem in board.iter_mut().flatten() {
+                *gem = rng.range(0, colors) as u8 + 1;
+            }
+            board
+        })
+    }
+
+    /// Random boards with no matches on them, as the board is whenever the game checks for
+    /// legal moves.
+    fn settled_boards() -> impl Iterator<Item = Board> {
+        random_boards().filter(|board| Bitboards::from_board(board).matches() == 0)
+    }
+
+    #[test]
+    fn marks_agree_with_loops() {
+        for board in random_boards() {
+            let mut game = game_with(board);
+            let mut oracle = game_with(board);
+            let marks = game.calculate_marks();
+            let oracle_marks = oracle.loop_calculate_marks();
+            for (col, oracle_col) in oracle_marks.iter().enumerate() {
+                for (row, oracle_mark) in oracle_col.iter().enumerate() {
+                    assert_eq!(marks & bitboard::cell(col, row) != 0, *oracle_mark != 0);
+                }
+            }
+            assert_eq!(game.score, oracle.score);
+            assert_eq!(game.popups, oracle.popups);
+        }
+    }
+
+    #[test]
+    fn swaps_agree_with_loops() {
+        for board in settled_boards() {
+            let mut game = game_with(board);
+            for col in 0..BOARD_WIDTH {
+                for row in 0..BOARD_HEIGHT {
+                    for dir in DIRECTIONS {
+                        game.cursor.set_cursor(col, row);
+                        game.swap_cursor(dir);
+                        assert_eq!(game.makes_match(dir), game.loop_makes_match(dir));
+                        game.swap_cursor(dir);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn game_over_agrees_with_loops() {
+        let mut checked = 0;
+        for board in settled_boards() {
+            let mut game = game_with(board);
+            let mut oracle = game_with(board);
+            game.check_for_game_over();
+            oracle.loop_check_for_game_over();
+            assert_eq!(game.alive, oracle.alive);
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn diagonal_board_is_game_over() {
+        let mut board = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+        for (col, column) in board.iter_mut().enumerate() {
+            for (row, gem) in column.iter_mut().enumerate() {
+                *gem = ((col + row) % GEM_COUNT) as u8 + 1;
+            }
+        }
+        let mut game = game_with(board);
+        let mut oracle = game_with(board);
+        game.check_for_game_over();
+        oracle.loop_check_for_game_over();
+        assert!(!game.alive);
+        assert!(!oracle.alive);
+    }
+
+    #[test]
+    fn legal_moves_agree_with_bitboards() {
+        for board in settled_boards() {
+            let game = game_with(board);
+            let moves = game.legal_moves();
+            assert_eq!(!moves.is_empty(), Bitboards::from_board(&board).has_legal_move());
+            for mv in moves {
+                let mut played = game_with(board);
+                assert!(played.play(mv));
+                assert_ne!(played.board, board);
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        for board in settled_boards() {
+            let game = game_with(board);
+            let legal = game.legal_moves();
+            for col in 0..BOARD_WIDTH {
+                for row in 0..BOARD_HEIGHT {
+                    for dir in DIRECTIONS {
+                        let mv = Move { col, row, dir };
+                        let mirrored = mv.other().map(|(c, r)| Move { col: c, row: r, dir: opposite(dir) });
+                        if legal.contains(&mv) || mirrored.is_some_and(|mirrored| legal.contains(&mirrored)) {
+                            continue;
+                        }
+                        let mut played = game_with(board);
+                        assert!(!played.play(mv));
+                        assert_eq!(played.board, board);
+                    }
+                }
+            }
+        }
+    }
+
+    fn opposite(dir: Direction) -> Direction {
+        match dir {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    #[test]
+    fn games_settle_before_each_move() {
+        let mut game = Game::new(320, GemPolicy::Uniform);
+        let mut tick = 0;
+        for _ in 0..20 {
+            while !game.is_settled() {
+                tick += 1;
+                game.tick(tick);
+            }
+            assert_eq!(Bitboards::from_board(&game.board).matches(), 0);
+            match game.legal_moves().first() {
+                Some(mv) => assert!(game.play(*mv)),
+                None => {
+                    assert!(!game.is_alive());
+                    break;
+                }
+            }
+            assert!(!game.is_settled());
+        }
+    }
+
+    #[test]
+    fn swaps_wait_for_cleared_gems_to_flash() {
+        let board = settled_boards().find(|board| Bitboards::from_board(board).has_legal_move()).unwrap();
+        let mut game = game_with(board);
+        let mv = game.legal_moves()[0];
+        game.clearing = Some(Clearing { marks: bitboard::cell(0, 0), ticks_left: CLEAR_TICKS });
+        game.cursor.set_cursor(mv.col, mv.row);
+        game.selected = true;
+        game.do_action(InputAction::Move(mv.dir));
+        assert_eq!(game.board, board);
+        assert!(game.swap.is_none());
+        assert!(game.take_played().is_none());
+    }
+
+    #[test]
+    fn empty_cells_never_match() {
+        let mut board = [[1; BOARD_HEIGHT]; BOARD_WIDTH];
+        for column in board.iter_mut() {
+            for gem in column.iter_mut().take(3) {
+                *gem = 0;
+            }
+        }
+        let boards = Bitboards::from_board(&board);
+        assert_eq!(boards.matches() & bitboard::cell(0, 0), 0);
+        assert_ne!(boards.matches() & bitboard::cell(0, 3), 0);
+    }
+
+    #[test]
+    fn garbage_rises_from_the_bottom() {
+        let mut game = Game::new(320, GemPolicy::Uniform);
+        let mut tick = 0;
+        while !game.is_settled() {
+            tick += 1;
+            game.tick(tick);
+        }
+        let before = game.board;
+        game.add_garbage(1);
+        assert!(game.is_settled());
+        while game.get_pending_garbage() > 0 || !game.is_settled() {
+            tick += 1;
+            game.tick(tick);
+        }
+        for (column, old) in game.board.iter().zip(before.iter()) {
+            assert_eq!(column[..BOARD_HEIGHT - 1], old[1..]);
+            assert_eq!(column[BOARD_HEIGHT - 1], STONE);
+        }
+        assert_eq!(game.is_alive(), Bitboards::from_board(&game.board).has_legal_move());
+    }
+
+    #[test]
+    fn matches_clear_the_stones_beside_them() {
+        let mut board = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+        for (col, column) in board.iter_mut().enumerate() {
+            for (row, gem) in column.iter_mut().enumerate() {
+                *gem = ((col + row) % GEM_COUNT) as u8 + 1;
+            }
+            column[BOARD_HEIGHT - 1] = STONE;
+        }
+        // three 1s just above the stones, at columns 3 to 5
+        for column in &mut board[3..6] {
+            column[BOARD_HEIGHT - 2] = 1;
+        }
+        let mut game = game_with(board);
+        game.score_matches();
+        for col in 0..BOARD_WIDTH {
+            let cleared = game.get_clearing(col, BOARD_HEIGHT - 1).is_some();
+            assert_eq!(cleared, (3..6).contains(&col), "stone in column {}", col);
+        }
+        assert_eq!(game.take_garbage(), 0);
+        // stones cannot be swapped into a match
+        assert_eq!(game.move_points(Move { col: 2, row: BOARD_HEIGHT - 1, dir: Direction::Right }), 0);
+    }
+
+    /// Passes the bytes each manager has for the link to the other, until neither has any.
+    fn exchange(a: &mut GameStateManager, b: &mut GameStateManager) {
+        loop {
+            let (to_b, to_a) = (a.take_link_output(), b.take_link_output());
+            if to_a.is_empty() && to_b.is_empty() { return; }
+            to_b.iter().for_each(|byte| b.link_byte(*byte));
+            to_a.iter().for_each(|byte| a.link_byte(*byte));
+        }
+    }
+
+    #[test]
+    fn versus_games_trade_garbage() {
+        let mut a = GameStateManager::new(|| 1);
+        let mut b = GameStateManager::new(|| 2);
+        a.key(DecodedKey::RawKey(KeyCode::F5));
+        b.key(DecodedKey::RawKey(KeyCode::F5));
+        assert_eq!(a.get_state(), GameState::Connecting);
+        exchange(&mut a, &mut b);
+        assert_eq!(a.get_state(), GameState::Playing);
+        assert_eq!(b.get_state(), GameState::Playing);
+        assert_eq!(a.get_seed(), b.get_seed());
+        // a long match on a's board
+        a.game.garbage_out = 2;
+        a.tick(1);
+        exchange(&mut a, &mut b);
+        assert_eq!(a.get_versus().unwrap().lines_sent, 2);
+        assert_eq!(b.get_versus().unwrap().lines_received, 2);
+        assert_eq!(b.get_game().get_pending_garbage(), 2);
+        // b gives up, which forfeits
+        b.key(DecodedKey::Unicode(0x1b as char));
+        b.choose_pause_option(PauseOption::Quit);
+        exchange(&mut a, &mut b);
+        assert!(b.get_versus().is_none());
+        let versus = a.get_versus().unwrap();
+        assert!(versus.opponent_over);
+        assert_eq!(versus.outcome, Some(Outcome::Won));
+    }
+
+    /// Two managers playing a versus game against each other.
+    fn versus_pair() -> (GameStateManager, GameStateManager) {
+        let mut a = GameStateManager::new(|| 1);
+        let mut b = GameStateManager::new(|| 2);
+        a.key(DecodedKey::RawKey(KeyCode::F5));
+        b.key(DecodedKey::RawKey(KeyCode::F5));
+        exchange(&mut a, &mut b);
+        (a, b)
+    }
+
+    #[test]
+    fn a_lost_game_over_is_sent_again() {
+        let (mut a, mut b) = versus_pair();
+        a.game.alive = false;
+        a.tick(1);
+        // the frame with a's GameOver is lost
+        a.take_link_output();
+        for tick in 2..2 + crate::link::RESEND_PERIOD {
+            a.tick(tick);
+        }
+        exchange(&mut a, &mut b);
+        assert_eq!(a.get_versus().unwrap().outcome, Some(Outcome::Lost));
+        assert_eq!(b.get_versus().unwrap().outcome, Some(Outcome::Won));
+    }
+
+    #[test]
+    fn a_silent_opponent_forfeits() {
+        let (mut a, _) = versus_pair();
+        for tick in 1..=crate::link::SILENCE_LIMIT {
+            a.tick(tick);
+        }
+        let versus = a.get_versus().unwrap();
+        assert!(versus.opponent_gone && versus.opponent_over);
+        assert_eq!(versus.outcome, Some(Outcome::Won));
+    }
+
+    #[test]
+    fn serial_console_turns_on_when_it_is_used() {
+        let mut gsm = GameStateManager::new(|| 1);
+        assert_eq!(gsm.get_settings().serial, SerialOutput::Off);
+        gsm.serial_console_seen();
+        assert_eq!(gsm.get_settings().serial, SerialOutput::Mirror);
+        // a choice made with F4 is kept
+        let mut gsm = GameStateManager::new(|| 1);
+        gsm.key(DecodedKey::RawKey(KeyCode::F4));
+        gsm.key(DecodedKey::RawKey(KeyCode::F4));
+        assert_eq!(gsm.get_settings().serial, SerialOutput::Only);
+        gsm.serial_console_seen();
+        assert_eq!(gsm.get_settings().serial, SerialOutput::Only);
+    }
+}
@@ -0,0 +1,211 @@
+// Invariants of the game engine, checked with random seeds and inputs by the property tests
+// below and by the fuzz target in match3-core/fuzz.
+//
+// check() plays a Game through do_action() and tick() the way the keyboard and timer would,
+// with garbage arriving as if from an opponent, and after every step asserts that:
+// - once drop_step() returns false, no gem sits above an empty cell
+// - the score never goes down
+// - do_action() never leaves a swap on the board unless it makes a match, and never moves a stone
+// - the cursor stays on the board
+// - whenever the board settles, check_for_game_over() agrees with trying every swap by hand
+//
+// The hand-written checks here deliberately avoid the bitboards, so that they can catch
+// mistakes in them.
+
+use super::*;
+
+/// One thing a player can do between checks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    Action(InputAction),
+    /// Makes legal move number n, counting around if there are fewer, as the simulator does.
+    Play(u8),
+    /// Lets this many ticks pass.
+    Wait(u8),
+    /// Sends this many lines of garbage, as an opponent would.
+    Garbage(u8),
+}
+
+impl Step {
+    /// Decodes one byte of fuzzer input. Most bytes wait or play a legal move, so that games
+    /// get far enough for gems to fall, cascade and run out of moves. Garbage is rarer, so that
+    /// stones do not end every game at once.
+    pub fn from_byte(byte: u8) -> Self {
+        match byte % 8 {
+            0 => Step::Action(InputAction::Move(Direction::Up)),
+            1 => Step::Action(InputAction::Move(Direction::Down)),
+            2 => Step::Action(InputAction::Move(Direction::Left)),
+            3 => Step::Action(InputAction::Move(Direction::Right)),
+            4 => Step::Action(InputAction::Select),
+            5 => Step::Play(byte / 8),
+            7 if byte < 32 => Step::Garbage(1),
+            _ => Step::Wait(byte / 8 + 1),
+        }
+    }
+}
+
+/// Plays **steps** in the game dealt from **seed** with **policy**, and panics as soon as an
+/// invariant breaks.
+pub fn check(seed: u64, policy: GemPolicy, steps: &[Step]) {
+    let mut game = Game::new(seed, policy);
+    let mut tick = 0;
+    for step in steps {
+        match *step {
+            Step::Action(action) => {
+                let (before, score) = (game.board, game.score);
+                game.do_action(action);
+                check_swap(&before, &game.board);
+                assert!(game.score >= score, "the score went down");
+            },
+            Step::Play(n) => {
+                let moves = game.legal_moves();
+                if moves.is_empty() { continue; }
+                let before = game.board;
+                game.play(moves[n as usize % moves.len()]);
+                check_swap(&before, &game.board);
+            },
+            Step::Garbage(lines) => game.add_garbage(lines as usize),
+            Step::Wait(ticks) => {
+                for _ in 0..ticks {
+                    let score = game.score;
+                    tick += 1;
+                    game.tick(tick);
+                    assert!(game.score >= score, "the score went down");
+                    check_drop(&game);
+                    if game.settled {
+                        assert_eq!(game.alive, has_move(&game.board), "game over disagrees with a search of every swap");
+                    }
+                }
+            },
+        }
+        let (col, row) = game.cursor.location();
+        assert!(col < BOARD_WIDTH && row < BOARD_HEIGHT, "the cursor left the board at ({}, {})", col, row);
+    }
+}
+
+/// Checks that a drop_step() that moves nothing leaves no gem above an empty cell.
+fn check_drop(game: &Game) {
+    let mut probe = game.clone();
+    if probe.drop_step() { return; }
+    for (col, column) in probe.board.iter().enumerate() {
+        for row in 0..BOARD_HEIGHT - 1 {
+            assert!(column[row] == 0 || column[row + 1] != 0, "the gem at ({}, {}) floats over an empty cell", col, row);
+        }
+    }
+}
+
+/// Checks that going from **before** to **after** at most swapped two neighbors, and only to
+/// make a match.
+fn check_swap(before: &Board, after: &Board) {
+    let mut changed = Vec::new();
+    for col in 0..BOARD_WIDTH {
+        for row in 0..BOARD_HEIGHT {
+            if before[col][row] != after[col][row] {
+                changed.push((col, row));
+            }
+        }
+    }
+    if changed.is_empty() { return; }
+    assert_eq!(changed.len(), 2, "an action changed {} cells", changed.len());
+    let (a, b) = (changed[0], changed[1]);
+    assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1, "{:?} and {:?} were swapped but are not neighbors", a, b);
+    assert_eq!((after[a.0][a.1], after[b.0][b.1]), (before[b.0][b.1], before[a.0][a.1]), "an action changed gems without swapping them");
+    assert!(after[a.0][a.1] != STONE && after[b.0][b.1] != STONE, "the swap of {:?} and {:?} moved a stone", a, b);
+    assert!(in_match(after, a.0, a.1) || in_match(after, b.0, b.1), "the swap of {:?} and {:?} was kept without making a match", a, b);
+}
+
+type Board = [[u8; BOARD_HEIGHT]; BOARD_WIDTH];
+
+/// Returns whether any swap of neighbors on **board** makes a match, by trying each one.
+fn has_move(board: &Board) -> bool {
+    let mut board = *board;
+    for col in 0..BOARD_WIDTH {
+        for row in 0..BOARD_HEIGHT {
+            for (c, r) in [(col + 1, row), (col, row + 1)] {
+                if c >= BOARD_WIDTH || r >= BOARD_HEIGHT { continue; }
+                // stones cannot be swapped
+                if board[col][row] == STONE || board[c][r] == STONE { continue; }
+                let temp = board[col][row];
+                board[col][row] = board[c][r];
+                board[c][r] = temp;
+                let found = in_match(&board, col, row) || in_match(&board, c, r);
+                board[c][r] = board[col][row];
+                board[col][row] = temp;
+                if found { return true; }
+            }
+        }
+    }
+    false
+}
+
+/// Returns whether the gem at (col, row) is in a line of three or more of its color. Stones
+/// never match.
+fn in_match(board: &Board, col: usize, row: usize) -> bool {
+    let gem = board[col][row];
+    if gem == 0 || gem == STONE { return false; }
+    let same = |c: usize, r: usize| board[c][r] == gem;
+    let up = (0..row).rev().take_while(|r| same(col, *r)).count();
+    let down = (row + 1..BOARD_HEIGHT).take_while(|r| same(col, *r)).count();
+    let left = (0..col).rev().take_while(|c| same(*c, row)).count();
+    let right = (col + 1..BOARD_WIDTH).take_while(|c| same(*c, row)).count();
+    up + down + 1 >= 3 || left + right + 1 >= 3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const DIRECTIONS: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            3 => prop::sample::select(&DIRECTIONS[..]).prop_map(|dir| Step::Action(InputAction::Move(dir))),
+            1 => Just(Step::Action(InputAction::Select)),
+            2 => any::<u8>().prop_map(Step::Play),
+            4 => (1..=32u8).prop_map(Step::Wait),
+            1 => (1..=2u8).prop_map(Step::Garbage),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn random_play_keeps_the_invariants(
+            seed in any::<u64>(),
+            policy in prop::sample::select(&GemPolicy::ALL[..]),
+            steps in prop::collection::vec(step(), 0..300),
+        ) {
+            check(seed, policy, &steps);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "without making a match")]
+    fn swaps_without_a_match_are_caught() {
+        let mut before = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+        before[0][7] = 1;
+        before[1][7] = 2;
+        let mut after = before;
+        after[0][7] = 2;
+        after[1][7] = 1;
+        check_swap(&before, &after);
+    }
+
+    #[test]
+    fn search_finds_a_move() {
+        let mut board = [[0; BOARD_HEIGHT]; BOARD_WIDTH];
+        for (col, column) in board.iter_mut().enumerate() {
+            for (row, gem) in column.iter_mut().enumerate() {
+                *gem = ((col + row) % GEM_COUNT) as u8 + 1;
+            }
+        }
+        assert!(!has_move(&board));
+        // two 1s in the top row, and a third that one swap brings up beside them
+        board[0][0] = 1;
+        board[1][0] = 1;
+        board[2][1] = 1;
+        assert!(has_move(&board));
+    }
+}
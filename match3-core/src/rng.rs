@@ -0,0 +1,153 @@
+// Random number generators for dealing gems.
+//
+// xoshiro256** and its jump function are by David Blackman and Sebastiano Vigna:
+// https://prng.di.unimi.it/xoshiro256starstar.c
+// PCG32 (XSH-RR) is by Melissa O'Neill: https://www.pcg-random.org/download.html
+// SplitMix64, used to expand a 64-bit seed into a full generator state, is by Sebastiano Vigna:
+// https://prng.di.unimi.it/splitmix64.c
+
+/// A source of pseudorandom numbers.
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a number in **min** up to (not including) **max**, without modulo bias.
+    fn range(&mut self, min: u64, max: u64) -> u64 {
+        let range = max - min;
+        let limit = u64::MAX / range * range;
+        let mut candidate = self.next_u64();
+        while candidate >= limit {
+            candidate = self.next_u64();
+        }
+        candidate % range + min
+    }
+}
+
+/// A generator that can be rebuilt exactly from a seed, and split into independent streams.
+pub trait SeedableRng: Rng + Sized {
+    fn from_seed(seed: u64) -> Self;
+
+    /// Returns generator number **stream** for **seed**. Different streams of the same seed do
+    /// not overlap, so what is drawn from one never changes what another produces.
+    fn stream(seed: u64, stream: u64) -> Self;
+}
+
+/// Expands a seed into a sequence of well-mixed values.
+pub struct SplitMix64 {
+    state: u64
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// xoshiro256**: fast, with a period of 2^256 - 1. Streams are separated by jumps of 2^128 steps.
+#[derive(Clone)]
+pub struct Xoshiro256StarStar {
+    s: [u64; 4]
+}
+
+impl Xoshiro256StarStar {
+    /// Advances the generator by 2^128 steps.
+    pub fn jump(&mut self) {
+        const JUMP: [u64; 4] = [0x180ec6d33cfd0aba, 0xd5a61266f0c9392c, 0xa9582618e03fc9aa, 0x39abdc4529b1661c];
+        let mut s = [0u64; 4];
+        for jump in JUMP {
+            for b in 0..64 {
+                if jump & (1 << b) != 0 {
+                    for (acc, word) in s.iter_mut().zip(self.s.iter()) {
+                        *acc ^= *word;
+                    }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = s;
+    }
+}
+
+impl Rng for Xoshiro256StarStar {
+    fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+}
+
+impl SeedableRng for Xoshiro256StarStar {
+    fn from_seed(seed: u64) -> Self {
+        // SplitMix64 never yields four zeros in a row, which is the one state xoshiro can't use.
+        let mut seeder = SplitMix64::new(seed);
+        Xoshiro256StarStar { s: [seeder.next_u64(), seeder.next_u64(), seeder.next_u64(), seeder.next_u64()] }
+    }
+
+    fn stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self::from_seed(seed);
+        for _ in 0..stream {
+            rng.jump();
+        }
+        rng
+    }
+}
+
+/// PCG32 (XSH-RR): a small state with 2^63 selectable streams built in.
+#[derive(Clone)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    fn new(seed: u64, sequence: u64) -> Self {
+        let mut rng = Pcg32 { state: 0, increment: (sequence << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+}
+
+impl Rng for Pcg32 {
+    fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        (high << 32) | self.next_u32() as u64
+    }
+}
+
+impl SeedableRng for Pcg32 {
+    fn from_seed(seed: u64) -> Self {
+        Self::stream(seed, 0)
+    }
+
+    fn stream(seed: u64, stream: u64) -> Self {
+        // the stream selects the increment, so streams never share a sequence
+        Pcg32::new(seed, stream)
+    }
+}
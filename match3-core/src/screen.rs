@@ -0,0 +1,277 @@
+// An 80x25 grid of colored characters, and the drawing operations on it.
+//
+// The Screen trait is implemented by the kernel's VGA Writer, by TextBuffer in memory, and by
+// ansi.rs's AnsiMirror for a terminal, so that the screens in draw.rs can be drawn on any of them
+// and checked in tests without VGA memory. Color and ColorCode are the VGA text-mode attributes.
+//
+// The provided drawing methods are Gabriel Ferrer's vga_buffer functions:
+// clear_row(), clear_screen(), plot_str(), clear(), plot_num(), plot_num_right_justified()
+// and num_str_len().
+
+use alloc::string::String;
+
+pub const BUFFER_HEIGHT: usize = 25;
+pub const BUFFER_WIDTH: usize = 80;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+/// Represents a 4-bit x86 color code
+pub enum Color {
+    Black = 0,
+    Blue = 1,
+    Green = 2,
+    Cyan = 3,
+    Red = 4,
+    Magenta = 5,
+    Brown = 6,
+    LightGray = 7,
+    DarkGray = 8,
+    LightBlue = 9,
+    LightGreen = 10,
+    LightCyan = 11,
+    LightRed = 12,
+    Pink = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+impl From<u8> for Color {
+    // I attempted to use the enum-repr crate instead of doing this, but it did not compile.
+    fn from(n: u8) -> Self {
+        use Color::*;
+        match n {
+            0 => Black, 1 => Blue, 2 => Green, 3 => Cyan, 4 => Red, 5 => Magenta, 6 => Brown,
+            7 => LightGray, 8 => DarkGray, 9 => LightBlue, 10 => LightGreen, 11 => LightCyan,
+            12 => LightRed, 13 => Pink, 14 => Yellow, 15 => White,
+            _ => panic!("Undefined color value: {}", n)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+/// Represents two 4-bit x86 colors: a foreground and a background
+pub struct ColorCode(u8);
+
+impl ColorCode {
+    pub const fn new(foreground: Color, background: Color) -> ColorCode {
+        ColorCode((background as u8) << 4 | (foreground as u8))
+    }
+
+    pub fn foreground(&self) -> Color {
+        Color::from(self.0 & 0xF)
+    }
+
+    pub fn background(&self) -> Color {
+        Color::from((self.0 & 0xF0) >> 4)
+    }
+}
+
+/// Something that shows a grid of colored characters.
+pub trait Screen {
+    /// Returns the size of the screen as (columns, rows).
+    fn size(&self) -> (usize, usize);
+
+    /// Plots the given character at the given location with the given color.
+    /// It will **panic** on an illegal row or column.
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode);
+
+    /// Returns the character and color at the specified coordinates.
+    ///
+    /// It will **panic** given an illegal row or column.
+    fn peek(&self, col: usize, row: usize) -> (char, ColorCode);
+
+    /// Clears one row, setting everything to the background color specified.
+    /// It will **panic** on an illegal row.
+    fn clear_row(&mut self, row: usize, background: Color) {
+        let color = ColorCode::new(background, background);
+        for col in 0..self.size().0 {
+            self.plot(' ', col, row, color);
+        }
+    }
+
+    /// Sets all rows to Black.
+    fn clear_screen(&mut self) {
+        for row in 0..self.size().1 {
+            self.clear_row(row, Color::Black);
+        }
+    }
+
+    /// Displays the specified string at the given coordinates.
+    /// If the string exceeds the width of the screen, it will be truncated.
+    /// An illegal row will **panic**.
+    fn plot_str(&mut self, s: &str, col: usize, row: usize, color: ColorCode) -> usize {
+        let width = self.size().0;
+        let end = width.min(col + s.len());
+        for (c, chr) in (col..end).zip(s.chars()) {
+            self.plot(chr, c, row, color);
+        }
+        end % width
+    }
+
+    /// Clears a certain number of spaces.
+    /// Returns the next column to use after the call.
+    /// It will **panic** on an illegal row.
+    fn clear(&mut self, num_spaces: usize, col: usize, row: usize, color: ColorCode) -> usize {
+        let width = self.size().0;
+        let end = width.min(col + num_spaces);
+        for c in col..end {
+            self.plot(' ', c, row, color);
+        }
+        end % width
+    }
+
+    /// Displays the given number at the specified coordinates.
+    /// Returns the next column to use after the call.
+    ///
+    /// If the number exceeds the width of the screen, it will be truncated.
+    ///
+    /// It will **panic** if an illegal row is given.
+    fn plot_num(&mut self, num: isize, col: usize, row: usize, color: ColorCode) -> usize {
+        let width = self.size().0;
+        if num == 0 {
+            self.plot('0', col, row, color);
+            (col + 1) % width
+        } else if num < 0 {
+            self.plot('-', col, row, color);
+            self.plot_num(-num, col + 1, row, color)
+        } else {
+            let mut buffer = [' '; BUFFER_WIDTH];
+            let mut c = 0;
+            let mut num = num;
+            while num > 0 && c + col < width.min(buffer.len()) {
+                buffer[c] = ((num % 10 + '0' as isize) as u8) as char;
+                num /= 10;
+                c += 1;
+            }
+            for (i, digit) in buffer[..c].iter().enumerate() {
+                self.plot(*digit, col + c - i - 1, row, color);
+            }
+            (col + c) % width
+        }
+    }
+
+    /// Displays the given number at the specified coordinates.
+    /// Returns the next column to use after the call.
+    ///
+    /// It will pad the number with spaces to its left so that it occupies **total_space** columns.
+    /// If the number requires more columns than **total_spaces**, it will spill over to the right,
+    /// thus foiling right-justification.
+    ///
+    /// If the number exceeds the width of the screen, it will be truncated.
+    ///
+    /// It will **panic** if an illegal row is given.
+    fn plot_num_right_justified(&mut self, total_space: usize, num: isize, col: usize, row: usize, color: ColorCode) -> usize {
+        let space_needed = num_str_len(num);
+        let leading_spaces = total_space.saturating_sub(space_needed);
+        if leading_spaces > 0 {
+            self.clear(leading_spaces, col, row, ColorCode::new(color.background(), color.background()));
+        }
+        self.plot_num(num, col + leading_spaces, row, color)
+    }
+}
+
+impl<S: Screen + ?Sized> Screen for &mut S {
+    fn size(&self) -> (usize, usize) {
+        (**self).size()
+    }
+
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        (**self).plot(c, col, row, color)
+    }
+
+    fn peek(&self, col: usize, row: usize) -> (char, ColorCode) {
+        (**self).peek(col, row)
+    }
+}
+
+/// Returns the length **num** would have when plotted.
+pub fn num_str_len(num: isize) -> usize {
+    if num == 0 {
+        1
+    } else if num < 0 {
+        1 + num_str_len(-num)
+    } else {
+        let mut num = num;
+        let mut c = 0;
+        while num > 0 {
+            num /= 10;
+            c += 1;
+        }
+        c
+    }
+}
+
+/// A screen kept in memory, for drawing a frame before it is shown and for checking what was
+/// drawn in tests.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TextBuffer {
+    cells: [[(char, ColorCode); BUFFER_WIDTH]; BUFFER_HEIGHT],
+}
+
+impl TextBuffer {
+    /// A cleared screen.
+    pub fn new() -> Self {
+        TextBuffer { cells: [[(' ', ColorCode::new(Color::Black, Color::Black)); BUFFER_WIDTH]; BUFFER_HEIGHT] }
+    }
+
+    /// Returns the characters on the screen, one line per row, without trailing spaces.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for row in self.cells.iter() {
+            let line: String = row.iter().map(|(c, _)| *c).collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Default for TextBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Screen for TextBuffer {
+    fn size(&self) -> (usize, usize) {
+        (BUFFER_WIDTH, BUFFER_HEIGHT)
+    }
+
+    fn plot(&mut self, c: char, col: usize, row: usize, color: ColorCode) {
+        self.cells[row][col] = (c, color);
+    }
+
+    fn peek(&self, col: usize, row: usize) -> (char, ColorCode) {
+        self.cells[row][col]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: ColorCode = ColorCode::new(Color::White, Color::Blue);
+
+    #[test]
+    fn numbers_are_justified_and_truncated() {
+        let mut screen = TextBuffer::new();
+        assert_eq!(screen.plot_num_right_justified(6, -45, 0, 0, CODE), 6);
+        assert_eq!(screen.plot_num(1234, BUFFER_WIDTH - 2, 1, CODE), 0);
+        let text = screen.text();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("   -45"));
+        // only the lowest digits fit
+        assert!(lines.next().unwrap().ends_with(" 34"));
+        assert_eq!(screen.peek(0, 0), (' ', ColorCode::new(Color::Blue, Color::Blue)));
+    }
+
+    #[test]
+    fn strings_stop_at_the_right_edge() {
+        let mut screen = TextBuffer::new();
+        assert_eq!(screen.plot_str("Match3", BUFFER_WIDTH - 3, 2, CODE), 0);
+        assert_eq!(screen.peek(BUFFER_WIDTH - 1, 2), ('t', CODE));
+        assert_eq!(screen.peek(0, 3).0, ' ');
+    }
+}
@@ -0,0 +1,258 @@
+// Turns the bytes a terminal sends into the key events the kernel's keyboard driver reports.
+//
+// Printable keys arrive as themselves, and Enter, Tab, Backspace and Escape as control
+// characters. Arrow and function keys arrive as escape sequences, which vary a little between
+// terminals. A terminal never says when a key comes back up, so each arrow press is followed
+// at once by its release; while an arrow is held, the terminal's own auto-repeat sends more.
+//
+// The bytes may come all at once, as match3-term reads them, or one at a time, as the kernel
+// receives them on its serial port. Decoder keeps the start of an escape sequence until the
+// rest arrives, and decides that an Escape with nothing after it was the Escape key itself.
+
+use alloc::vec::Vec;
+use pc_keyboard::{DecodedKey, KeyCode, KeyState};
+use crate::input::{Modifiers, RawKeyEvent};
+
+const ESCAPE: u8 = 0x1b;
+const CTRL_C: u8 = 0x03;
+const CTRL_L: u8 = 0x0c;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+/// The longest escape sequence decoded, ESC [ 2 4 ~.
+const MAX_SEQUENCE: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    Key(DecodedKey),
+    Raw(RawKeyEvent),
+    /// Ctrl-C, which ends the program.
+    Quit,
+    /// Ctrl-L, which asks for the whole screen to be sent again.
+    Redraw,
+}
+
+/// Decodes a stream of terminal bytes, which may split escape sequences anywhere.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    /// An escape sequence that has started but not finished.
+    pending: [u8; MAX_SEQUENCE],
+    len: usize,
+    /// Whether a tick has passed since the last byte arrived.
+    waited: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes **byte**, passing any inputs it completes to **out**.
+    pub fn push(&mut self, byte: u8, out: &mut impl FnMut(Input)) {
+        self.waited = false;
+        if self.len == 0 && byte != ESCAPE {
+            control_or_char(byte, out);
+            return;
+        }
+        self.pending[self.len] = byte;
+        self.len += 1;
+        self.decode_pending(false, out);
+    }
+
+    /// Decodes whatever is pending as if nothing more will follow it, so that a lone Escape is
+    /// the Escape key.
+    pub fn flush(&mut self, out: &mut impl FnMut(Input)) {
+        self.decode_pending(true, out);
+    }
+
+    /// Call once per timer tick, for input that arrives a byte at a time. An escape sequence
+    /// still unfinished after a whole tick is flushed, since a terminal sends every byte of one
+    /// together.
+    pub fn tick(&mut self, out: &mut impl FnMut(Input)) {
+        if self.len > 0 && self.waited {
+            self.flush(out);
+        }
+        self.waited = true;
+    }
+
+    fn decode_pending(&mut self, finished: bool, out: &mut impl FnMut(Input)) {
+        while self.len > 0 {
+            // pending always starts with an Escape
+            match escape_sequence(&self.pending[1..self.len]) {
+                Sequence::Key(code, len) => {
+                    press(code, out);
+                    self.consume(1 + len);
+                },
+                Sequence::Incomplete if !finished => return,
+                Sequence::Incomplete | Sequence::Invalid => {
+                    out(Input::Key(DecodedKey::Unicode(ESCAPE as char)));
+                    self.consume(1);
+                    // the bytes after a stray Escape are ordinary input, up to the next Escape
+                    while self.len > 0 && self.pending[0] != ESCAPE {
+                        control_or_char(self.pending[0], out);
+                        self.consume(1);
+                    }
+                },
+            }
+        }
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.pending.copy_within(count..self.len, 0);
+        self.len -= count;
+    }
+}
+
+/// Decodes everything in **bytes**. An escape sequence cut off at the end of **bytes** is read as
+/// the Escape key followed by the characters after it.
+pub fn parse(bytes: &[u8]) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    let mut decoder = Decoder::new();
+    let mut out = |input| inputs.push(input);
+    for byte in bytes {
+        decoder.push(*byte, &mut out);
+    }
+    decoder.flush(&mut out);
+    inputs
+}
+
+fn control_or_char(byte: u8, out: &mut impl FnMut(Input)) {
+    match byte {
+        CTRL_C => out(Input::Quit),
+        CTRL_L => out(Input::Redraw),
+        b'\r' | b'\n' => out(Input::Key(DecodedKey::Unicode('\n'))),
+        BACKSPACE | DELETE => out(Input::Key(DecodedKey::Unicode(BACKSPACE as char))),
+        b'\t' | b' '..=b'~' => out(Input::Key(DecodedKey::Unicode(byte as char))),
+        _ => {}
+    }
+}
+
+/// Reports **code** the way the kernel does: a raw press, the decoded key, and then, since the
+/// terminal will not send one, a raw release.
+fn press(code: KeyCode, out: &mut impl FnMut(Input)) {
+    let event = |state| Input::Raw(RawKeyEvent { code, state, modifiers: Modifiers::default() });
+    out(event(KeyState::Down));
+    out(Input::Key(DecodedKey::RawKey(code)));
+    out(event(KeyState::Up));
+}
+
+/// What the bytes after an Escape turned out to be.
+enum Sequence {
+    /// A key, and how many bytes after the Escape it used.
+    Key(KeyCode, usize),
+    /// The start of a sequence that more bytes could finish.
+    Incomplete,
+    /// Not a known sequence.
+    Invalid,
+}
+
+/// Reads the escape sequence at the start of **bytes**, which follow an Escape byte.
+fn escape_sequence(bytes: &[u8]) -> Sequence {
+    let arrow = |final_byte| match final_byte {
+        b'A' => Some(KeyCode::ArrowUp),
+        b'B' => Some(KeyCode::ArrowDown),
+        b'C' => Some(KeyCode::ArrowRight),
+        b'D' => Some(KeyCode::ArrowLeft),
+        _ => None,
+    };
+    let found = |key: Option<KeyCode>, len| key.map_or(Sequence::Invalid, |key| Sequence::Key(key, len));
+    match bytes {
+        [] | [b'[' | b'O'] | [b'[', b'['] => Sequence::Incomplete,
+        // the Linux console's function keys
+        [b'[', b'[', key @ b'A'..=b'D', ..] => found(function_key((key - b'A' + 1) as usize), 3),
+        [b'[' | b'O', key, ..] if arrow(*key).is_some() => found(arrow(*key), 2),
+        [b'O', key @ b'P'..=b'S', ..] => found(function_key((key - b'P' + 1) as usize), 2),
+        [b'[', rest @ ..] => {
+            // ESC [ number ~, as xterm sends for F1 to F12
+            let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+            match rest.get(digits) {
+                None if digits > 0 && digits < MAX_SEQUENCE - 2 => return Sequence::Incomplete,
+                Some(b'~') if digits > 0 => {},
+                _ => return Sequence::Invalid,
+            }
+            let number = rest[..digits].iter().fold(0, |n, digit| n * 10 + (digit - b'0') as usize);
+            let key = match number {
+                11..=15 => number - 10,
+                17..=21 => number - 11,
+                23 | 24 => number - 12,
+                _ => return Sequence::Invalid,
+            };
+            found(function_key(key), digits + 2)
+        },
+        _ => Sequence::Invalid,
+    }
+}
+
+fn function_key(number: usize) -> Option<KeyCode> {
+    use KeyCode::*;
+    [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12].get(number.checked_sub(1)?).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<DecodedKey> {
+        parse(bytes).into_iter().filter_map(|input| match input {
+            Input::Key(key) => Some(key),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn control_characters_match_the_kernel() {
+        assert_eq!(keys(b"a\r\t\x7f"), [
+            DecodedKey::Unicode('a'), DecodedKey::Unicode('\n'), DecodedKey::Unicode('\t'),
+            DecodedKey::Unicode('\x08')
+        ]);
+    }
+
+    #[test]
+    fn arrows_are_pressed_and_released() {
+        let inputs = parse(b"\x1b[A");
+        assert_eq!(inputs.len(), 3);
+        assert!(matches!(inputs[0], Input::Raw(event) if event.code == KeyCode::ArrowUp && event.is_press()));
+        assert_eq!(inputs[1], Input::Key(DecodedKey::RawKey(KeyCode::ArrowUp)));
+        assert!(matches!(inputs[2], Input::Raw(event) if event.code == KeyCode::ArrowUp && event.is_release()));
+        assert_eq!(keys(b"\x1bOD"), [DecodedKey::RawKey(KeyCode::ArrowLeft)]);
+    }
+
+    #[test]
+    fn function_keys_from_several_terminals() {
+        for bytes in [&b"\x1bOR"[..], b"\x1b[13~", b"\x1b[[C"] {
+            assert_eq!(keys(bytes), [DecodedKey::RawKey(KeyCode::F3)]);
+        }
+        assert_eq!(keys(b"\x1b[24~"), [DecodedKey::RawKey(KeyCode::F12)]);
+    }
+
+    #[test]
+    fn lone_escape_is_the_escape_key() {
+        assert_eq!(keys(b"\x1b"), [DecodedKey::Unicode('\x1b')]);
+        assert_eq!(keys(b"\x1bq"), [DecodedKey::Unicode('\x1b'), DecodedKey::Unicode('q')]);
+        assert_eq!(keys(b"\x1b[9"), [DecodedKey::Unicode('\x1b'), DecodedKey::Unicode('['), DecodedKey::Unicode('9')]);
+    }
+
+    #[test]
+    fn control_keys() {
+        assert_eq!(parse(b"\x03\x0c"), [Input::Quit, Input::Redraw]);
+    }
+
+    #[test]
+    fn sequences_split_across_ticks() {
+        let mut decoder = Decoder::new();
+        let mut inputs = Vec::new();
+        let mut out = |input| inputs.push(input);
+        for byte in b"\x1b[1" {
+            decoder.push(*byte, &mut out);
+        }
+        // one tick is not long enough to give up on the rest
+        decoder.tick(&mut out);
+        for byte in b"3~\x1b" {
+            decoder.push(*byte, &mut out);
+        }
+        decoder.tick(&mut out);
+        decoder.tick(&mut out);
+        assert_eq!(inputs.len(), 4);
+        assert_eq!(inputs[1], Input::Key(DecodedKey::RawKey(KeyCode::F3)));
+        assert_eq!(inputs[3], Input::Key(DecodedKey::Unicode('\x1b')));
+    }
+}
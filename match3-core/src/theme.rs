@@ -0,0 +1,97 @@
+// Color themes for the 16 text-mode colors.
+//
+// A theme is just the RGB value of each color. The kernel loads it into the VGA palette, and
+// other frontends can draw with the RGB values directly.
+
+/// Number of remappable text-mode colors.
+pub const PALETTE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An 8-bit-per-channel RGB color. The DAC only stores the top 6 bits of each channel.
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+
+    /// Builds a color from a 0xRRGGBB literal.
+    pub const fn from_hex(hex: u32) -> Self {
+        Rgb::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+    }
+
+    fn luminance(&self) -> u8 {
+        ((self.r as u16 * 77 + self.g as u16 * 150 + self.b as u16 * 29) >> 8) as u8
+    }
+}
+
+const CLASSIC: [Rgb; PALETTE_SIZE] = [
+    Rgb::from_hex(0x000000), Rgb::from_hex(0x0000AA), Rgb::from_hex(0x00AA00), Rgb::from_hex(0x00AAAA),
+    Rgb::from_hex(0xAA0000), Rgb::from_hex(0xAA00AA), Rgb::from_hex(0xAA5500), Rgb::from_hex(0xAAAAAA),
+    Rgb::from_hex(0x555555), Rgb::from_hex(0x5555FF), Rgb::from_hex(0x55FF55), Rgb::from_hex(0x55FFFF),
+    Rgb::from_hex(0xFF5555), Rgb::from_hex(0xFF55FF), Rgb::from_hex(0xFFFF55), Rgb::from_hex(0xFFFFFF),
+];
+
+const PASTEL: [Rgb; PALETTE_SIZE] = [
+    Rgb::from_hex(0x202028), Rgb::from_hex(0x6C7FA8), Rgb::from_hex(0x78A878), Rgb::from_hex(0x70A8A8),
+    Rgb::from_hex(0xA87070), Rgb::from_hex(0xA078A8), Rgb::from_hex(0xA89070), Rgb::from_hex(0xB0B0B8),
+    Rgb::from_hex(0x606070), Rgb::from_hex(0xA8C0F8), Rgb::from_hex(0xB8F0B8), Rgb::from_hex(0xB0F0F0),
+    Rgb::from_hex(0xF8B0B0), Rgb::from_hex(0xF0B8F0), Rgb::from_hex(0xF8F0B0), Rgb::from_hex(0xF8F8F8),
+];
+
+const HIGH_CONTRAST: [Rgb; PALETTE_SIZE] = [
+    Rgb::from_hex(0x000000), Rgb::from_hex(0x0000C0), Rgb::from_hex(0x00C000), Rgb::from_hex(0x00C0C0),
+    Rgb::from_hex(0xC00000), Rgb::from_hex(0xC000C0), Rgb::from_hex(0xC08000), Rgb::from_hex(0xC0C0C0),
+    Rgb::from_hex(0x404040), Rgb::from_hex(0x0000FF), Rgb::from_hex(0x00FF00), Rgb::from_hex(0x00FFFF),
+    Rgb::from_hex(0xFF0000), Rgb::from_hex(0xFF00FF), Rgb::from_hex(0xFFFF00), Rgb::from_hex(0xFFFFFF),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Predefined color themes for the 16 text-mode colors.
+pub enum Theme {
+    Classic,
+    Pastel,
+    HighContrast,
+    MonochromeGreen,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Classic, Theme::Pastel, Theme::HighContrast, Theme::MonochromeGreen];
+
+    /// Returns the theme after this one, wrapping around to the first.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|t| *t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "Classic",
+            Theme::Pastel => "Pastel",
+            Theme::HighContrast => "High Contrast",
+            Theme::MonochromeGreen => "Monochrome Green",
+        }
+    }
+
+    /// Returns the RGB values of this theme, indexed by Color value.
+    pub fn palette(self) -> [Rgb; PALETTE_SIZE] {
+        match self {
+            Theme::Classic => CLASSIC,
+            Theme::Pastel => PASTEL,
+            Theme::HighContrast => HIGH_CONTRAST,
+            Theme::MonochromeGreen => {
+                // Shades of phosphor green, ordered by the brightness of the classic colors.
+                let mut palette = CLASSIC;
+                for rgb in palette.iter_mut() {
+                    let level = rgb.luminance();
+                    *rgb = Rgb::new(level / 6, level, level / 4);
+                }
+                palette
+            }
+        }
+    }
+}
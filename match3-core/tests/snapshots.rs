@@ -0,0 +1,132 @@
+// Golden-text snapshots of the text-mode screens, drawn on a TextBuffer.
+//
+// Each test drives a GameStateManager with keys and ticks the way the kernel does, draws one
+// screen and compares it with a file in tests/snapshots. The file has the screen's text, then
+// its foreground and background colors as one hex digit per cell, since gems differ only in
+// color. After changing how a screen looks, rewrite the files with
+//     UPDATE_SNAPSHOTS=1 cargo test -p match3-core --test snapshots
+// and check the difference before committing it.
+
+use std::path::PathBuf;
+use match3_core::date::{Date, DateTime};
+use match3_core::draw::{self, LOGO_COLORS};
+use match3_core::match3game::{Direction, GameState, GameStateManager, Move};
+use match3_core::screen::{ColorCode, Screen, TextBuffer};
+use pc_keyboard::DecodedKey;
+
+const NOW: DateTime = DateTime { date: Date { year: 2024, month: 3, day: 14 }, hour: 9, minute: 26, second: 53 };
+/// Long enough for any cascade to finish.
+const MAX_SETTLE_TICKS: usize = 10_000;
+
+/// Returns the text, foreground and background of **screen** as they are stored in a snapshot.
+fn snapshot(screen: &TextBuffer) -> String {
+    let (width, height) = screen.size();
+    let layer = |digit: fn(ColorCode) -> u8| -> String {
+        (0..height).map(|row| {
+            let mut line: String = (0..width).map(|col| format!("{:x}", digit(screen.peek(col, row).1))).collect();
+            line.push('\n');
+            line
+        }).collect()
+    };
+    format!("{}\nforeground:\n{}\nbackground:\n{}", screen.text(),
+        layer(|code| code.foreground() as u8), layer(|code| code.background() as u8))
+}
+
+/// Compares **screen** with tests/snapshots/**name**.txt, or rewrites the file if
+/// UPDATE_SNAPSHOTS is set.
+fn assert_snapshot(name: &str, screen: &TextBuffer) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", &format!("{}.txt", name)].iter().collect();
+    let actual = snapshot(screen);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {}; run with UPDATE_SNAPSHOTS=1 to create it", path.display(), err));
+    if actual != expected {
+        let first = actual.lines().zip(expected.lines()).position(|(a, e)| a != e).unwrap_or(0);
+        panic!("{} no longer matches {}, first at row {}.\n\nDrawn:\n{}\nExpected:\n{}\n\
+                Run with UPDATE_SNAPSHOTS=1 if the change is intended.", name, path.display(), first, actual, expected);
+    }
+}
+
+/// A manager on the code menu, with the clock set and no randomness.
+fn manager() -> GameStateManager {
+    let mut gsm = GameStateManager::new(|| 0x5eed);
+    gsm.set_clock(NOW);
+    gsm
+}
+
+fn type_str(gsm: &mut GameStateManager, s: &str) {
+    for c in s.chars() {
+        gsm.key(DecodedKey::Unicode(c));
+    }
+}
+
+/// Ticks until the board stops moving.
+fn settle(gsm: &mut GameStateManager, tick: &mut u64) {
+    for _ in 0..MAX_SETTLE_TICKS {
+        *tick += 1;
+        gsm.tick(*tick);
+        if gsm.get_game().is_settled() { return; }
+    }
+    panic!("the board did not settle");
+}
+
+/// Makes **mv** the way a player would: walking the cursor to it, selecting, and swapping.
+fn play(gsm: &mut GameStateManager, mv: Move, tick: &mut u64) {
+    let key = |dir| match dir {
+        Direction::Up => "w",
+        Direction::Down => "s",
+        Direction::Left => "a",
+        Direction::Right => "d",
+    };
+    let (col, row) = gsm.get_game().get_cursor().location();
+    let horizontal = if mv.col < col { "a" } else { "d" };
+    let vertical = if mv.row < row { "w" } else { "s" };
+    type_str(gsm, &horizontal.repeat(col.abs_diff(mv.col)));
+    type_str(gsm, &vertical.repeat(row.abs_diff(mv.row)));
+    type_str(gsm, " ");
+    type_str(gsm, key(mv.dir));
+    settle(gsm, tick);
+}
+
+#[test]
+fn code_menu() {
+    let mut gsm = manager();
+    type_str(&mut gsm, "snapshot");
+    let mut screen = TextBuffer::new();
+    draw::draw_logo(&mut screen, &LOGO_COLORS);
+    draw::draw_code_menu(&mut screen, &gsm);
+    assert_snapshot("code_menu", &screen);
+}
+
+#[test]
+fn game() {
+    let mut gsm = manager();
+    let mut tick = 0;
+    type_str(&mut gsm, "snapshot\n");
+    settle(&mut gsm, &mut tick);
+    let mv = gsm.get_game().legal_moves()[0];
+    play(&mut gsm, mv, &mut tick);
+    assert!(gsm.get_game().get_score() > 0);
+    let mut screen = TextBuffer::new();
+    draw::draw_game(&mut screen, &gsm, tick);
+    assert_snapshot("game", &screen);
+}
+
+#[test]
+fn game_over() {
+    let mut gsm = manager();
+    let mut tick = 0;
+    type_str(&mut gsm, "snapshot\n");
+    settle(&mut gsm, &mut tick);
+    while gsm.get_game().is_alive() {
+        let mv = gsm.get_game().legal_moves()[0];
+        play(&mut gsm, mv, &mut tick);
+    }
+    assert_eq!(gsm.get_state(), GameState::Playing);
+    let mut screen = TextBuffer::new();
+    draw::draw_game(&mut screen, &gsm, tick);
+    assert_snapshot("game_over", &screen);
+}
@@ -0,0 +1,190 @@
+//! Plays Match3 headlessly on the host, for tuning the gem count, scoring and refill rules.
+//!
+//! `run` plays a batch of seeds with a computer player and reports the mean and median score,
+//! the game length in moves, how deep the cascades went, and how often a settled board had no
+//! legal move. `search` plays many typed codes and lists the ones that best fit a goal, such as the
+//! longest game. Every game is run tick by tick through the same match3-core code as the kernel,
+//! and every reported code replays its game when typed on the menu screen.
+
+mod player;
+mod report;
+mod stats;
+
+use std::thread;
+use match3_core::gem_generator::GemPolicy;
+use match3_core::match3game::{code_seed, seed_code, MAX_CODE_LEN};
+use player::{Ai, GameResult};
+use report::Format;
+use stats::{Goal, Summary};
+
+const USAGE: &str = "\
+Usage: match3-sim run [options]
+       match3-sim search [options]
+
+run plays a batch of seeds and prints summary statistics.
+  --games N        games to play (default 1000)
+  --first-seed N   seed of the first game; the rest follow in order (default 0)
+  --per-game       print one row per game instead of a summary
+
+search plays the codes PREFIX0, PREFIX1, ... and prints the ones that best fit a goal.
+  --codes N        codes to try (default 1000)
+  --prefix TEXT    start of every code (default \"seed\")
+  --goal GOAL      longest, shortest, highest-score, lowest-score or deepest-cascade
+                   (default longest)
+  --top N          codes to print (default 10)
+
+Both take:
+  --ai NAME        computer player: random, greedy or bottom (default greedy)
+  --gems POLICY    uniform, bag, weighted or mercy (default uniform); seed codes in a
+                   search choose their own
+  --max-moves N    stop a game after this many moves (default 10000)
+  --format FORMAT  csv or json (default csv)
+";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Command { Run, Search }
+
+struct Options {
+    command: Command,
+    games: u64,
+    first_seed: u64,
+    per_game: bool,
+    codes: u64,
+    prefix: String,
+    goal: Goal,
+    top: usize,
+    ai: Ai,
+    gems: GemPolicy,
+    max_moves: usize,
+    format: Format,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        print!("{}", USAGE);
+        return;
+    }
+    match parse_args(&args) {
+        Ok(options) => print!("{}", run(&options)),
+        Err(err) => {
+            eprintln!("match3-sim: {}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    let command = match args.next().map(String::as_str) {
+        Some("run") => Command::Run,
+        Some("search") => Command::Search,
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+        None => return Err(String::from("missing command")),
+    };
+    let mut options = Options {
+        command,
+        games: 1000,
+        first_seed: 0,
+        per_game: false,
+        codes: 1000,
+        prefix: String::from("seed"),
+        goal: Goal::Longest,
+        top: 10,
+        ai: Ai::Greedy,
+        gems: GemPolicy::Uniform,
+        max_moves: 10000,
+        format: Format::Csv,
+    };
+    while let Some(arg) = args.next() {
+        if arg == "--per-game" {
+            options.per_game = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = || format!("invalid value '{}' for {}", value, arg);
+        match arg.as_str() {
+            "--games" => options.games = value.parse().map_err(|_| invalid())?,
+            "--first-seed" => options.first_seed = parse_seed(value).ok_or_else(invalid)?,
+            "--codes" => options.codes = value.parse().map_err(|_| invalid())?,
+            "--prefix" => options.prefix = value.clone(),
+            "--goal" => options.goal = Goal::from_name(value).ok_or_else(invalid)?,
+            "--top" => options.top = value.parse().map_err(|_| invalid())?,
+            "--ai" => options.ai = Ai::from_name(value).ok_or_else(invalid)?,
+            "--gems" => options.gems = parse_policy(value).ok_or_else(invalid)?,
+            "--max-moves" => options.max_moves = value.parse().map_err(|_| invalid())?,
+            "--format" => options.format = Format::from_name(value).ok_or_else(invalid)?,
+            _ => return Err(format!("unknown option '{}'", arg)),
+        }
+    }
+    let longest_code = options.prefix.len() + options.codes.saturating_sub(1).to_string().len();
+    if options.command == Command::Search && longest_code > MAX_CODE_LEN {
+        return Err(format!("codes can be at most {} characters long", MAX_CODE_LEN));
+    }
+    Ok(options)
+}
+
+/// Reads a seed in decimal, or in hexadecimal after 0x or #.
+fn parse_seed(value: &str) -> Option<u64> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix('#')) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// Reads a gem policy by its letter in seed codes, or by its name.
+fn parse_policy(value: &str) -> Option<GemPolicy> {
+    let value = value.to_ascii_lowercase();
+    GemPolicy::ALL.iter().copied().find(|policy| {
+        value == policy_name(*policy) || value == policy.name().to_ascii_lowercase() || value == policy.letter().to_string()
+    })
+}
+
+/// The name of **policy** as --gems takes it, such as "bag" for 7-bag.
+fn policy_name(policy: GemPolicy) -> String {
+    policy.name().to_ascii_lowercase().trim_start_matches("7-").to_string()
+}
+
+fn run(options: &Options) -> String {
+    match options.command {
+        Command::Run => {
+            let games = (0..options.games).map(|i| {
+                let seed = options.first_seed.wrapping_add(i);
+                (seed_code(seed, options.gems), seed, options.gems)
+            }).collect();
+            let results = play_all(games, options.ai, options.max_moves);
+            if options.per_game {
+                report::games(options.format, &results)
+            } else {
+                let summary = Summary::of(&results);
+                report::summary(options.format, options.ai.name(), &policy_name(options.gems), &summary)
+            }
+        },
+        Command::Search => {
+            let games = (0..options.codes).map(|i| {
+                let code = format!("{}{}", options.prefix, i);
+                let (seed, policy) = code_seed(&code, options.gems);
+                (code, seed, policy)
+            }).collect();
+            let mut results = play_all(games, options.ai, options.max_moves);
+            options.goal.sort(&mut results);
+            results.truncate(options.top);
+            report::games(options.format, &results)
+        },
+    }
+}
+
+/// Plays every (code, seed, policy) in **games**, spread over all the host's cores. The results
+/// are in the same order as **games**.
+fn play_all(games: Vec<(String, u64, GemPolicy)>, ai: Ai, max_moves: usize) -> Vec<GameResult> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = games.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = games.chunks(chunk_size).map(|chunk| scope.spawn(move || {
+            chunk.iter()
+                .map(|(code, seed, policy)| player::play(code.clone(), *seed, *policy, ai, max_moves))
+                .collect::<Vec<_>>()
+        })).collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("a game panicked")).collect()
+    })
+}
@@ -0,0 +1,141 @@
+// Computer players, and playing one game to the end with them.
+//
+// A game is run through Game::tick exactly as the kernel runs it, one timer tick at a time, so
+// swaps, flashing matches and falling gems all take as long as they do on screen. The player
+// only moves once the board has settled, which a person could do faster but never needs to.
+
+use match3_core::gem_generator::GemPolicy;
+use match3_core::match3game::{Game, Move, BOARD_WIDTH};
+use match3_core::rng::{Rng, SeedableRng, Xoshiro256StarStar};
+
+/// How a computer player chooses among the legal moves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ai {
+    /// Any legal move, chosen at random.
+    Random,
+    /// The move that scores the most at once, ignoring what falls afterwards.
+    Greedy,
+    /// The move lowest on the board, which disturbs the most gems above it.
+    Bottom,
+}
+
+impl Ai {
+    pub const ALL: [Ai; 3] = [Ai::Random, Ai::Greedy, Ai::Bottom];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ai::Random => "random",
+            Ai::Greedy => "greedy",
+            Ai::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|ai| ai.name() == name)
+    }
+}
+
+/// What happened in one game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GameResult {
+    /// The code that replays the game.
+    pub code: String,
+    /// The final score, as the game shows it.
+    pub score: usize,
+    pub moves: usize,
+    /// For each move, how many rounds of matches it set off: 1 for a move whose matches leave
+    /// no new ones behind, 2 if the falling gems make one more round, and so on.
+    pub cascades: Vec<usize>,
+    /// Whether the game ended with no legal move left, rather than by reaching the move limit.
+    pub dead: bool,
+}
+
+impl GameResult {
+    /// The longest cascade of the game, or 0 if no move was made.
+    pub fn deepest_cascade(&self) -> usize {
+        self.cascades.iter().copied().max().unwrap_or(0)
+    }
+}
+
+/// Plays the game dealt from **seed** and **policy** with **ai**, until no legal move is left
+/// or **max_moves** have been made. **code** is reported as the code of the game.
+pub fn play(code: String, seed: u64, policy: GemPolicy, ai: Ai, max_moves: usize) -> GameResult {
+    let mut game = Game::new(seed, policy);
+    // the random player draws from a stream that no column deals from
+    let mut rng = Xoshiro256StarStar::stream(seed, BOARD_WIDTH as u64);
+    let mut tick = 0;
+    let mut result = GameResult { code, score: 0, moves: 0, cascades: Vec::new(), dead: false };
+    // matches made while the board first fills are scored, but are not anyone's move
+    settle(&mut game, &mut tick);
+    while result.moves < max_moves {
+        let moves = game.legal_moves();
+        if !game.is_alive() || moves.is_empty() {
+            result.dead = true;
+            break;
+        }
+        let mv = choose(ai, &game, &moves, &mut rng);
+        game.play(mv);
+        result.moves += 1;
+        result.cascades.push(settle(&mut game, &mut tick));
+    }
+    result.score = game.get_score() * 100;
+    result
+}
+
+fn choose(ai: Ai, game: &Game, moves: &[Move], rng: &mut impl Rng) -> Move {
+    match ai {
+        Ai::Random => moves[rng.range(0, moves.len() as u64) as usize],
+        Ai::Greedy => *moves.iter().max_by_key(|mv| game.move_points(**mv)).unwrap_or(&moves[0]),
+        Ai::Bottom => *moves.iter().max_by_key(|mv| {
+            let lowest = mv.other().map_or(mv.row, |(_, row)| row.max(mv.row));
+            (lowest, game.move_points(**mv))
+        }).unwrap_or(&moves[0]),
+    }
+}
+
+/// Ticks **game** until its board settles. Returns how many rounds of matches were scored on
+/// the way.
+fn settle(game: &mut Game, tick: &mut u64) -> usize {
+    let mut rounds = 0;
+    while !game.is_settled() {
+        let score = game.get_score();
+        *tick += 1;
+        game.tick(*tick);
+        if game.get_score() > score {
+            rounds += 1;
+        }
+    }
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use match3_core::match3game::seed_code;
+
+    #[test]
+    fn games_are_repeatable() {
+        for ai in Ai::ALL {
+            let first = play(seed_code(7, GemPolicy::Bag), 7, GemPolicy::Bag, ai, 50);
+            let second = play(seed_code(7, GemPolicy::Bag), 7, GemPolicy::Bag, ai, 50);
+            assert_eq!(first, second);
+        }
+    }
+
+    #[test]
+    fn every_move_makes_a_match() {
+        let result = play(seed_code(1, GemPolicy::Uniform), 1, GemPolicy::Uniform, Ai::Random, 30);
+        assert_eq!(result.cascades.len(), result.moves);
+        assert!(result.cascades.iter().all(|depth| *depth >= 1));
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn move_limit_ends_the_game_alive() {
+        let result = play(seed_code(2, GemPolicy::Mercy), 2, GemPolicy::Mercy, Ai::Greedy, 3);
+        if !result.dead {
+            assert_eq!(result.moves, 3);
+        }
+        assert!(result.moves <= 3);
+    }
+}
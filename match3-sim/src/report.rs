@@ -0,0 +1,120 @@
+// Writes summaries and per-game results as CSV or JSON.
+//
+// CSV has a header row and one row per summary or game, so it loads straight into a
+// spreadsheet. JSON is a single object for a summary and an array of objects for games.
+
+use std::fmt::Write;
+use crate::player::GameResult;
+use crate::stats::Summary;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format { Csv, Json }
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Writes **summary** of games played by **ai** with gem policy **gems**.
+pub fn summary(format: Format, ai: &str, gems: &str, summary: &Summary) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Csv => {
+            out.push_str("ai,gems,games,mean_score,median_score,mean_moves,median_moves,dead_board_rate");
+            for depth in 1..=summary.cascades.len() {
+                let _ = write!(out, ",cascade_{}", depth);
+            }
+            let _ = write!(out, "\n{},{},{},{:.1},{:.1},{:.1},{:.1},{:.4}",
+                csv_field(ai), csv_field(gems), summary.games, summary.mean_score, summary.median_score,
+                summary.mean_moves, summary.median_moves, summary.dead_board_rate);
+            for count in &summary.cascades {
+                let _ = write!(out, ",{}", count);
+            }
+            out.push('\n');
+        },
+        Format::Json => {
+            let cascades: Vec<String> = summary.cascades.iter().map(|count| count.to_string()).collect();
+            let _ = writeln!(out,
+                "{{\"ai\":{},\"gems\":{},\"games\":{},\"mean_score\":{:.1},\"median_score\":{:.1},\"mean_moves\":{:.1},\
+                 \"median_moves\":{:.1},\"cascades\":[{}],\"dead_board_rate\":{:.4}}}",
+                json_string(ai), json_string(gems), summary.games, summary.mean_score, summary.median_score,
+                summary.mean_moves, summary.median_moves, cascades.join(","), summary.dead_board_rate);
+        },
+    }
+    out
+}
+
+/// Writes one row or object per game.
+pub fn games(format: Format, results: &[GameResult]) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Csv => {
+            out.push_str("code,score,moves,deepest_cascade,dead\n");
+            for result in results {
+                let _ = writeln!(out, "{},{},{},{},{}",
+                    csv_field(&result.code), result.score, result.moves, result.deepest_cascade(), result.dead);
+            }
+        },
+        Format::Json => {
+            let objects: Vec<String> = results.iter().map(|result| format!(
+                "{{\"code\":{},\"score\":{},\"moves\":{},\"deepest_cascade\":{},\"dead\":{}}}",
+                json_string(&result.code), result.score, result.moves, result.deepest_cascade(), result.dead
+            )).collect();
+            let _ = writeln!(out, "[{}]", objects.join(",\n "));
+        },
+    }
+    out
+}
+
+/// Quotes **field** if it holds anything that CSV would otherwise split on.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(code: &str) -> GameResult {
+        GameResult { code: code.into(), score: 1200, moves: 2, cascades: vec![1, 3], dead: true }
+    }
+
+    #[test]
+    fn codes_are_escaped() {
+        assert_eq!(games(Format::Csv, &[result("a,\"b\"")]), "code,score,moves,deepest_cascade,dead\n\"a,\"\"b\"\"\",1200,2,3,true\n");
+        assert_eq!(games(Format::Json, &[result("a\"\\\x01")]),
+            "[{\"code\":\"a\\\"\\\\\\u0001\",\"score\":1200,\"moves\":2,\"deepest_cascade\":3,\"dead\":true}]\n");
+    }
+
+    #[test]
+    fn summary_has_a_column_per_cascade_depth() {
+        let csv = summary(Format::Csv, "greedy", "7-bag", &Summary::of(&[result("a")]));
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().ends_with(",cascade_1,cascade_2,cascade_3"));
+        assert_eq!(lines.next().unwrap(), "greedy,7-bag,1,1200.0,1200.0,2.0,2.0,0.3333,1,0,1");
+    }
+}
@@ -0,0 +1,151 @@
+// Statistics over many games, and the properties that seed searches look for.
+
+use crate::player::GameResult;
+
+/// Summary statistics for a batch of games.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Summary {
+    pub games: usize,
+    pub mean_score: f64,
+    pub median_score: f64,
+    pub mean_moves: f64,
+    pub median_moves: f64,
+    /// How many moves set off each depth of cascade: index 0 counts moves with one round of
+    /// matches, index 1 those with two, and so on.
+    pub cascades: Vec<usize>,
+    /// The fraction of settled boards, the first one of each game and one after each move, that
+    /// had no legal move. Every game that stops before the move limit stops on a dead board.
+    pub dead_board_rate: f64,
+}
+
+impl Summary {
+    pub fn of(results: &[GameResult]) -> Self {
+        let scores: Vec<usize> = results.iter().map(|result| result.score).collect();
+        let moves: Vec<usize> = results.iter().map(|result| result.moves).collect();
+        let mut cascades = Vec::new();
+        for depth in results.iter().flat_map(|result| &result.cascades) {
+            if *depth == 0 { continue; }
+            if cascades.len() < *depth {
+                cascades.resize(*depth, 0);
+            }
+            cascades[depth - 1] += 1;
+        }
+        let dead = results.iter().filter(|result| result.dead).count();
+        let boards = results.iter().map(|result| result.moves + 1).sum::<usize>();
+        Summary {
+            games: results.len(),
+            mean_score: mean(&scores),
+            median_score: median(&scores),
+            mean_moves: mean(&moves),
+            median_moves: median(&moves),
+            cascades,
+            dead_board_rate: if results.is_empty() { 0.0 } else { dead as f64 / boards as f64 },
+        }
+    }
+}
+
+fn mean(values: &[usize]) -> f64 {
+    if values.is_empty() { return 0.0; }
+    values.iter().sum::<usize>() as f64 / values.len() as f64
+}
+
+/// The middle value, or the mean of the two middle values when there is an even number.
+fn median(values: &[usize]) -> f64 {
+    if values.is_empty() { return 0.0; }
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) as f64 / 2.0
+    } else {
+        sorted[mid] as f64
+    }
+}
+
+/// What a seed search looks for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Goal {
+    /// The most moves before the board runs out of them.
+    Longest,
+    /// The fewest moves before the board runs out of them.
+    Shortest,
+    HighestScore,
+    LowestScore,
+    /// The longest single cascade.
+    DeepestCascade,
+}
+
+impl Goal {
+    pub const ALL: [Goal; 5] = [Goal::Longest, Goal::Shortest, Goal::HighestScore, Goal::LowestScore, Goal::DeepestCascade];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Goal::Longest => "longest",
+            Goal::Shortest => "shortest",
+            Goal::HighestScore => "highest-score",
+            Goal::LowestScore => "lowest-score",
+            Goal::DeepestCascade => "deepest-cascade",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|goal| goal.name() == name)
+    }
+
+    /// Ranks **result** for this goal; higher is better.
+    fn rank(self, result: &GameResult) -> i64 {
+        match self {
+            Goal::Longest => result.moves as i64,
+            Goal::Shortest => -(result.moves as i64),
+            Goal::HighestScore => result.score as i64,
+            Goal::LowestScore => -(result.score as i64),
+            Goal::DeepestCascade => result.deepest_cascade() as i64,
+        }
+    }
+
+    /// Sorts **results** best first. Games that rank the same keep their order.
+    pub fn sort(self, results: &mut [GameResult]) {
+        results.sort_by_key(|result| -self.rank(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(code: &str, score: usize, cascades: &[usize], dead: bool) -> GameResult {
+        GameResult { code: code.into(), score, moves: cascades.len(), cascades: cascades.to_vec(), dead }
+    }
+
+    #[test]
+    fn medians_of_odd_and_even_counts() {
+        assert_eq!(median(&[5, 1, 3]), 3.0);
+        assert_eq!(median(&[4, 1, 3, 2]), 2.5);
+        assert_eq!(median(&[]), 0.0);
+    }
+
+    #[test]
+    fn summary_counts_cascades_by_depth() {
+        let results = [result("a", 900, &[1, 1, 3], true), result("b", 300, &[2], false)];
+        let summary = Summary::of(&results);
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.mean_score, 600.0);
+        assert_eq!(summary.median_moves, 2.0);
+        assert_eq!(summary.cascades, [2, 1, 1]);
+        // one of the six boards was dead
+        assert_eq!(summary.dead_board_rate, 1.0 / 6.0);
+    }
+
+    #[test]
+    fn goals_sort_best_first() {
+        let mut results = vec![result("a", 300, &[1, 1], true), result("b", 600, &[4], true), result("c", 0, &[1, 2, 1], true)];
+        Goal::Longest.sort(&mut results);
+        assert_eq!(results[0].code, "c");
+        Goal::HighestScore.sort(&mut results);
+        assert_eq!(results[0].code, "b");
+        Goal::Shortest.sort(&mut results);
+        assert_eq!(results[0].code, "b");
+        Goal::DeepestCascade.sort(&mut results);
+        assert_eq!(results[0].code, "b");
+    }
+}
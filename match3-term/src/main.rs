@@ -0,0 +1,101 @@
+//! Plays Match3 in a terminal on the host, without building a bootimage or starting QEMU.
+//!
+//! The game and its text-mode screens come from match3-core, so they are the same code the
+//! kernel runs. This frontend feeds the game timer ticks, keys and the time of day, and sends
+//! the screens to the terminal with ANSI escape codes. It needs a Unix terminal of at least
+//! 80x25 that supports 24-bit color. Ctrl-L redraws the screen and Ctrl-C quits.
+
+mod terminal;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::{Duration, Instant};
+use match3_core::date::{Date, DateTime};
+use match3_core::ansi::{AnsiMirror, Colors};
+use match3_core::draw;
+use match3_core::match3game::GameStateManager;
+use match3_core::screen::{Screen, BUFFER_HEIGHT, BUFFER_WIDTH};
+use match3_core::terminal_keys::{self, Input};
+use terminal::Terminal;
+
+/// The kernel leaves the PC's timer at its power-on rate of about 18.2 Hz, and the game's
+/// animations are timed in those ticks.
+const TICK_PERIOD: Duration = Duration::from_nanos(54_925_439);
+/// After a stall this long, such as a suspended process, ticks restart from now instead of
+/// catching up all at once.
+const MAX_LAG: Duration = Duration::from_secs(1);
+
+fn main() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        terminal::restore();
+        default_hook(info);
+    }));
+    if let Err(err) = run() {
+        eprintln!("match3-term: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    if cols < BUFFER_WIDTH || rows < BUFFER_HEIGHT {
+        return Err(io::Error::other(format!(
+            "the terminal is {}x{}, but the game needs at least {}x{}", cols, rows, BUFFER_WIDTH, BUFFER_HEIGHT
+        )));
+    }
+    let terminal = Terminal::open()?;
+    let mut gsm = GameStateManager::new(entropy);
+    let mut tick = 0;
+    let mut next_tick = Instant::now();
+    let mut screen = AnsiMirror::new(Colors::Rgb(gsm.get_settings().theme.palette()));
+    loop {
+        let input = terminal.read(next_tick.saturating_duration_since(Instant::now()))?;
+        for input in terminal_keys::parse(&input) {
+            match input {
+                Input::Key(key) => gsm.key(key),
+                Input::Raw(event) => gsm.raw_key(event),
+                Input::Redraw => screen.forget(),
+                Input::Quit => return Ok(()),
+            }
+        }
+        let now = Instant::now();
+        if now < next_tick {
+            continue;
+        }
+        next_tick = if now - next_tick > MAX_LAG { now } else { next_tick } + TICK_PERIOD;
+        tick += 1;
+        gsm.set_clock(local_time());
+        gsm.tick(tick);
+
+        screen.clear_screen();
+        draw::draw_screen(&mut screen, &gsm, tick);
+        screen.set_colors(Colors::Rgb(gsm.get_settings().theme.palette()));
+        let mut out = String::new();
+        // writing to a String cannot fail
+        let _ = screen.flush(&mut out);
+        terminal.write(out.as_bytes())?;
+    }
+}
+
+/// A fresh random number for seeding random games, from the randomness std uses for HashMap keys.
+fn entropy() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// The local date and time, as the kernel would read it from the RTC.
+fn local_time() -> DateTime {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        libc::localtime_r(&now, &mut tm);
+    }
+    DateTime {
+        date: Date { year: (tm.tm_year + 1900) as u16, month: (tm.tm_mon + 1) as u8, day: tm.tm_mday as u8 },
+        hour: tm.tm_hour as u8,
+        minute: tm.tm_min as u8,
+        // tm_sec can be 60 during a leap second
+        second: tm.tm_sec.min(59) as u8,
+    }
+}
@@ -0,0 +1,94 @@
+// Raw-mode terminal input and output on Unix.
+//
+// In raw mode the terminal hands over every key as soon as it is typed, without echoing it or
+// waiting for Enter, and Ctrl-C arrives as a byte instead of a signal. The game is drawn on the
+// alternate screen, so the shell's scrollback is untouched. restore() puts everything back; it
+// runs when the Terminal is dropped, and from the panic hook so that a panic message is readable.
+
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// The settings the terminal had before raw mode, for restore().
+static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+
+const ENTER_SCREEN: &[u8] = b"\x1b[?1049h\x1b[?25l\x1b[2J";
+const LEAVE_SCREEN: &[u8] = b"\x1b[0m\x1b[?25h\x1b[?1049l";
+
+pub struct Terminal(());
+
+impl Terminal {
+    /// Switches standard input to raw mode and standard output to the alternate screen.
+    pub fn open() -> io::Result<Self> {
+        let mut original = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = *ORIGINAL.get_or_init(|| original);
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let terminal = Terminal(());
+        terminal.write(ENTER_SCREEN)?;
+        Ok(terminal)
+    }
+
+    /// Waits up to **timeout** for input, and returns the bytes that have arrived, if any.
+    pub fn read(&self, timeout: Duration) -> io::Result<Vec<u8>> {
+        let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut poll, 1, timeout) } {
+            0 => return Ok(Vec::new()),
+            ready if ready < 0 => return interrupted_is_empty(io::Error::last_os_error()),
+            _ => {}
+        }
+        let mut buffer = [0u8; 64];
+        let count = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if count < 0 {
+            return interrupted_is_empty(io::Error::last_os_error());
+        }
+        Ok(buffer[..count as usize].to_vec())
+    }
+
+    pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        out.write_all(bytes)?;
+        out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Leaves the alternate screen and raw mode, if the terminal was put in them.
+pub fn restore() {
+    if let Some(original) = ORIGINAL.get() {
+        let mut out = io::stdout().lock();
+        let _ = out.write_all(LEAVE_SCREEN);
+        let _ = out.flush();
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, original) };
+    }
+}
+
+/// A signal such as SIGWINCH can cut a wait short; that is just a wait with no input.
+fn interrupted_is_empty(err: io::Error) -> io::Result<Vec<u8>> {
+    if err.kind() == io::ErrorKind::Interrupted {
+        Ok(Vec::new())
+    } else {
+        Err(err)
+    }
+}
+
+/// Returns the size of the terminal as (columns, rows).
+pub fn size() -> io::Result<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((size.ws_col as usize, size.ws_row as usize))
+}